@@ -2,31 +2,480 @@ import type { Principal } from '@dfinity/principal';
 import type { ActorMethod } from '@dfinity/agent';
 import type { IDL } from '@dfinity/candid';
 
-export type LockPeriod = { 'TwelveMonths' : null } |
+export interface AuditEntry {
+  'id' : bigint,
+  'time' : bigint,
+  'event' : AuditEvent,
+  'caller' : Principal,
+}
+export type AuditEvent = { 'MaturitySweepRun' : null } |
+  { 'Paused' : { 'operation' : Operation, 'reason' : string } } |
+  { 'RoleRevoked' : { 'principal' : Principal, 'role' : Role } } |
+  { 'Resumed' : { 'operation' : Operation } } |
+  { 'FeeRewardPoolShareSet' : { 'share_bps' : number } } |
+  { 'RoleGranted' : { 'principal' : Principal, 'role' : Role } } |
+  { 'ProductUpdated' : { 'product_id' : bigint } } |
+  { 'FeeScheduleSet' : { 'product_id' : [] | [bigint], 'operation' : FeeOperation } } |
+  { 'LoyaltyBonusRateSet' : { 'bonus_rate_bps' : number } } |
+  { 'ReferralSettingsSet' : null } |
+  { 'RewardPoolFunded' : { 'amount' : bigint } } |
+  { 'RateSheetPublished' : { 'rate_sheet_id' : bigint } } |
+  { 'PenaltyRewardPoolShareSet' : { 'share_bps' : number } } |
+  { 'ProductAdded' : { 'product_id' : bigint } };
+export interface CreateGoalArgs {
+  'name' : string,
+  'deadline' : [] | [bigint],
+  'target_amount' : bigint,
+  'category' : string,
+  'product' : [] | [ProductRef],
+}
+export interface DepositArgs {
+  'goal_id' : bigint,
+  'amount' : bigint,
+}
+export type DepositStatus = { 'Matured' : null } |
+  { 'Locked' : null };
+export interface DepositTerms {
+  'product_id' : bigint,
+  'early_withdrawal_penalty_bps' : number,
+  'duration_nanos' : bigint,
+  'interest_rate_bps' : number,
+}
+export type Error = { 'Paused' : { 'operation' : Operation, 'reason' : string } } |
+  { 'Rejected' : string } |
+  { 'Unauthenticated' : null };
+export type FeeOperation = { 'Burn' : null } |
+  { 'Mint' : null };
+export interface FeeSchedule {
+  'rate_bps' : number,
+  'min_fee' : bigint,
+  'max_fee' : [] | [bigint],
+}
+export interface FeeScheduleEntry {
+  'product_id' : [] | [bigint],
+  'operation' : FeeOperation,
+  'schedule' : FeeSchedule,
+}
+export interface FeeSettings {
+  'schedules' : Array<FeeScheduleEntry>,
+  'reward_pool_share_bps' : number,
+}
+export interface FeeTreasury {
+  'balance' : bigint,
+  'to_reward_pool' : bigint,
+  'burn_fees' : bigint,
+  'mint_fees' : bigint,
+}
+export type GoalStatus = { 'Active' : null } |
+  { 'Completed' : null };
+export interface InitArgs {
+  'owner' : Principal,
+  'auditors' : Array<Principal>,
+  'operators' : Array<Principal>,
+  'admins' : Array<Principal>,
+}
+export interface LoyaltySettings {
+  'bonus_rate_bps' : number,
+}
+export type MaturityInstruction = { 'RenewPrincipal' : null } |
+  { 'PayOut' : null } |
+  { 'RenewAs' : ProductRef } |
+  { 'Renew' : null };
+export interface MintResult {
+  'fee' : bigint,
+  'deposited' : bigint,
+  'token_amount' : bigint,
+  'deposit_id' : bigint,
+}
+export interface Notification {
+  'id' : bigint,
+  'goal_id' : [] | [bigint],
+  'read' : boolean,
+  'created_at' : bigint,
+  'user_id' : Principal,
+  'notification_type' : NotificationType,
+  'message' : string,
+}
+export type NotificationType = { 'DepositMatured' : null } |
+  { 'PenaltyApplied' : null } |
+  { 'DeadlineApproaching' : null } |
+  { 'GoalCompleted' : null } |
+  { 'RewardsCredited' : null } |
+  { 'MilestoneReached' : null };
+export type Operation = { 'Minting' : null } |
+  { 'Withdrawals' : null } |
+  { 'Rewards' : null };
+export interface Pause {
+  'paused_at' : bigint,
+  'paused_by' : Principal,
+  'reason' : string,
+}
+export interface PauseState {
+  'minting' : [] | [Pause],
+  'withdrawals' : [] | [Pause],
+  'rewards' : [] | [Pause],
+}
+export interface PenaltyAccount {
+  'total' : bigint,
+  'balance' : bigint,
+  'to_reward_pool' : bigint,
+  'by_product' : Array<[bigint, bigint]>,
+}
+export interface PenaltyAccountStatus {
+  'reward_pool_share_bps' : number,
+  'account' : PenaltyAccount,
+}
+export interface Product {
+  'id' : bigint,
+  'min_deposit' : bigint,
+  'max_deposit' : [] | [bigint],
+  'name' : string,
+  'open' : boolean,
+  'early_withdrawal_penalty_bps' : number,
+  'duration_nanos' : bigint,
+  'interest_rate_bps' : number,
+}
+export interface ProductArgs {
+  'min_deposit' : bigint,
+  'max_deposit' : [] | [bigint],
+  'name' : string,
+  'open' : boolean,
+  'early_withdrawal_penalty_bps' : number,
+  'duration_nanos' : bigint,
+  'interest_rate_bps' : number,
+}
+export interface ProductRates {
+  'product_id' : bigint,
+  'early_withdrawal_penalty_bps' : number,
+  'interest_rate_bps' : number,
+}
+export type ProductRef = { 'Id' : bigint } |
+  { 'TwelveMonths' : null } |
   { 'ThreeMonths' : null } |
   { 'SixMonths' : null };
+export interface RateSheet {
+  'id' : bigint,
+  'published_at' : bigint,
+  'effective_from' : bigint,
+  'rates' : Array<ProductRates>,
+}
+export interface Referral {
+  'status' : ReferralStatus,
+  'referrer' : Principal,
+  'redeemed_at' : bigint,
+  'referee' : Principal,
+}
+export interface ReferralEarnings {
+  'code' : [] | [string],
+  'referred_by' : [] | [Principal],
+  'referrals' : Array<Referral>,
+  'earned_as_referrer' : bigint,
+  'earned_as_referee' : bigint,
+}
+export interface ReferralSettings {
+  'min_deposit' : bigint,
+  'min_lock_nanos' : bigint,
+  'referrer_bonus' : bigint,
+  'referee_bonus' : bigint,
+}
+export type ReferralStatus = { 'Unqualified' : null } |
+  { 'Rewarded' : { 'referrer_bonus' : bigint, 'referee_bonus' : bigint } } |
+  { 'Unpaid' : null } |
+  { 'Pending' : null };
+export interface RewardPool {
+  'balance' : bigint,
+  'referral_bonuses' : bigint,
+  'fees' : bigint,
+  'funded' : bigint,
+  'loyalty_bonuses' : bigint,
+  'shortfall' : bigint,
+  'interest_paid' : bigint,
+  'forfeited_interest' : bigint,
+  'penalties' : bigint,
+}
+export interface RewardPoolStatus {
+  'pool' : RewardPool,
+  'uncovered' : bigint,
+  'projected_liabilities' : bigint,
+}
+export type Role = { 'Operator' : null } |
+  { 'Auditor' : null } |
+  { 'Admin' : null } |
+  { 'Owner' : null };
+export interface SavingsGoal {
+  'id' : bigint,
+  'status' : GoalStatus,
+  'name' : string,
+  'deadline' : [] | [bigint],
+  'created_at' : bigint,
+  'user_id' : Principal,
+  'current_amount' : bigint,
+  'target_amount' : bigint,
+  'category' : string,
+  'product' : ProductRef,
+  'deposit_ids' : Array<bigint>,
+}
 export interface TokenDeposit {
+  'id' : bigint,
+  'status' : DepositStatus,
+  'product_id' : bigint,
+  'accrual_remainder' : bigint,
+  'lock_duration_nanos' : bigint,
   'deposit_time' : bigint,
-  'early_withdrawal_penalty' : number,
-  'interest_rate' : number,
+  'early_withdrawal_penalty_bps' : number,
   'amount' : bigint,
-  'lock_period' : LockPeriod,
+  'maturity_instruction' : MaturityInstruction,
+  'partially_withdrawn' : boolean,
+  'last_accrual_time' : bigint,
+  'reward_index' : bigint,
+  'accrued_interest' : bigint,
+  'interest_rate_bps' : number,
+}
+export interface Transaction {
+  'id' : bigint,
+  'transaction_type' : TransactionType,
+  'goal_id' : [] | [bigint],
+  'note' : [] | [string],
+  'user_id' : Principal,
+  'timestamp' : bigint,
+  'manual' : boolean,
+  'amount' : bigint,
+}
+export interface TransactionSummary {
+  'contributions' : bigint,
+  'fees' : bigint,
+  'withdrawals' : bigint,
+  'rewards' : bigint,
+  'penalties' : bigint,
+}
+export type TransactionType = { 'Fee' : null } |
+  { 'Reward' : null } |
+  { 'Withdrawal' : null } |
+  { 'Contribution' : null } |
+  { 'Penalty' : null };
+export interface User {
+  'id' : Principal,
+  'updated_at' : bigint,
+  'username' : string,
+  'created_at' : bigint,
+  'email' : string,
 }
 export interface UserBalance {
   'available_balance' : bigint,
   'rewards_earned' : bigint,
   'locked_balance' : bigint,
+  'loyalty_streak' : number,
   'total_balance' : bigint,
   'deposits' : Array<TokenDeposit>,
+  'loyalty_bonus_earned' : bigint,
+}
+export interface WithdrawalLeg {
+  'fee' : bigint,
+  'deposit_id' : bigint,
+  'penalty' : bigint,
+  'product_id' : bigint,
+  'forfeited_interest' : bigint,
+  'amount' : bigint,
+}
+export interface WithdrawalQuote {
+  'net_payout' : bigint,
+  'legs' : Array<WithdrawalLeg>,
+  'total_fee' : bigint,
+  'total_penalty' : bigint,
+  'forfeited_interest' : bigint,
+  'amount' : bigint,
 }
+export type WithdrawalStrategy = { 'Fifo' : null } |
+  { 'Lifo' : null } |
+  { 'MaturedFirst' : null } |
+  { 'LeastPenalty' : null };
 export interface _SERVICE {
-  'apply_rewards' : ActorMethod<[], { 'Ok' : bigint } | { 'Err' : string }>,
-  'burn_tokens' : ActorMethod<[bigint], { 'Ok' : bigint } | { 'Err' : string }>,
-  'get_balance' : ActorMethod<[], { 'Ok' : UserBalance } | { 'Err' : string }>,
+  'add_product' : ActorMethod<
+    [ProductArgs],
+    { 'Ok' : bigint } |
+      { 'Err' : Error }
+  >,
+  'add_transaction' : ActorMethod<
+    [bigint, bigint, TransactionType, [] | [string]],
+    { 'Ok' : Transaction } |
+      { 'Err' : Error }
+  >,
+  'apply_rewards' : ActorMethod<[], { 'Ok' : bigint } | { 'Err' : Error }>,
+  'burn_tokens' : ActorMethod<
+    [bigint, [] | [WithdrawalStrategy]],
+    { 'Ok' : WithdrawalQuote } |
+      { 'Err' : Error }
+  >,
+  'create_referral_code' : ActorMethod<
+    [],
+    { 'Ok' : string } |
+      { 'Err' : Error }
+  >,
+  'create_savings_goal' : ActorMethod<
+    [CreateGoalArgs],
+    { 'Ok' : SavingsGoal } |
+      { 'Err' : Error }
+  >,
+  'deposit' : ActorMethod<
+    [DepositArgs],
+    { 'Ok' : SavingsGoal } |
+      { 'Err' : Error }
+  >,
+  'find_user' : ActorMethod<[string], [] | [Principal]>,
+  'fund_reward_pool' : ActorMethod<
+    [bigint],
+    { 'Ok' : bigint } |
+      { 'Err' : Error }
+  >,
+  'get_audit_log' : ActorMethod<
+    [],
+    { 'Ok' : Array<AuditEntry> } |
+      { 'Err' : Error }
+  >,
+  'get_balance' : ActorMethod<[], { 'Ok' : UserBalance } | { 'Err' : Error }>,
+  'get_custom_lock_terms' : ActorMethod<
+    [bigint],
+    { 'Ok' : DepositTerms } |
+      { 'Err' : Error }
+  >,
+  'get_fee_settings' : ActorMethod<[], FeeSettings>,
+  'get_fee_treasury' : ActorMethod<
+    [],
+    { 'Ok' : FeeTreasury } |
+      { 'Err' : Error }
+  >,
+  'get_loyalty_settings' : ActorMethod<[], LoyaltySettings>,
+  'get_my_roles' : ActorMethod<[], Array<Role>>,
+  'get_notifications' : ActorMethod<[boolean], Array<Notification>>,
+  'get_pause_state' : ActorMethod<[], PauseState>,
+  'get_penalty_account' : ActorMethod<
+    [],
+    { 'Ok' : PenaltyAccountStatus } |
+      { 'Err' : Error }
+  >,
+  'get_products' : ActorMethod<[], Array<Product>>,
+  'get_rate_sheets' : ActorMethod<[], Array<RateSheet>>,
+  'get_rates_at' : ActorMethod<[[] | [bigint]], Array<ProductRates>>,
+  'get_referral_earnings' : ActorMethod<[], ReferralEarnings>,
+  'get_referral_settings' : ActorMethod<[], ReferralSettings>,
+  'get_reward_indices' : ActorMethod<[], Array<[bigint, bigint]>>,
+  'get_reward_pool' : ActorMethod<
+    [],
+    { 'Ok' : RewardPoolStatus } |
+      { 'Err' : Error }
+  >,
+  'get_roles' : ActorMethod<
+    [],
+    { 'Ok' : Array<[Principal, Array<Role>]> } |
+      { 'Err' : Error }
+  >,
+  'get_savings_goal' : ActorMethod<[bigint], [] | [SavingsGoal]>,
+  'get_schema_version' : ActorMethod<[], number>,
+  'get_transaction' : ActorMethod<[bigint], [] | [Transaction]>,
+  'get_transaction_summary' : ActorMethod<
+    [bigint],
+    { 'Ok' : TransactionSummary } |
+      { 'Err' : Error }
+  >,
+  'get_user' : ActorMethod<[Principal], [] | [User]>,
+  'grant_role' : ActorMethod<
+    [Principal, Role],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'list_savings_goals' : ActorMethod<[], Array<SavingsGoal>>,
+  'list_transactions' : ActorMethod<[[] | [bigint]], Array<Transaction>>,
+  'mark_notification_as_read' : ActorMethod<
+    [bigint],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
   'mint_tokens' : ActorMethod<
-    [bigint, LockPeriod],
+    [bigint, ProductRef, [] | [MaturityInstruction]],
+    { 'Ok' : MintResult } |
+      { 'Err' : Error }
+  >,
+  'mint_tokens_until' : ActorMethod<
+    [bigint, bigint, [] | [MaturityInstruction]],
+    { 'Ok' : MintResult } |
+      { 'Err' : Error }
+  >,
+  'pause_operation' : ActorMethod<
+    [Operation, string],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'publish_rate_sheet' : ActorMethod<
+    [bigint, Array<ProductRates>],
     { 'Ok' : bigint } |
-      { 'Err' : string }
+      { 'Err' : Error }
+  >,
+  'quote_withdrawal' : ActorMethod<
+    [bigint, [] | [WithdrawalStrategy]],
+    { 'Ok' : WithdrawalQuote } |
+      { 'Err' : Error }
+  >,
+  'redeem_referral_code' : ActorMethod<
+    [string],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'register_user' : ActorMethod<
+    [string, string],
+    { 'Ok' : User } |
+      { 'Err' : Error }
+  >,
+  'resume_operation' : ActorMethod<
+    [Operation],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'revoke_role' : ActorMethod<
+    [Principal, Role],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'run_maturity_sweep' : ActorMethod<[], { 'Ok' : null } | { 'Err' : Error }>,
+  'set_fee_reward_pool_share' : ActorMethod<
+    [number],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'set_fee_schedule' : ActorMethod<
+    [FeeOperation, [] | [bigint], [] | [FeeSchedule]],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'set_loyalty_bonus_rate' : ActorMethod<
+    [number],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'set_maturity_instruction' : ActorMethod<
+    [bigint, MaturityInstruction],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'set_penalty_reward_pool_share' : ActorMethod<
+    [number],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'set_referral_settings' : ActorMethod<
+    [ReferralSettings],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'update_product' : ActorMethod<
+    [bigint, ProductArgs],
+    { 'Ok' : null } |
+      { 'Err' : Error }
+  >,
+  'update_user' : ActorMethod<[string], { 'Ok' : User } | { 'Err' : Error }>,
+  'withdraw_deposit' : ActorMethod<
+    [bigint, bigint],
+    { 'Ok' : WithdrawalQuote } |
+      { 'Err' : Error }
   >,
 }
 export declare const idlFactory: IDL.InterfaceFactory;
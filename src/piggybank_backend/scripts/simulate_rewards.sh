@@ -22,7 +22,7 @@ apply_rewards() {
 burn_tokens() {
     amount=$1
     echo "Burning $amount tokens..."
-    dfx canister call piggybank_backend burn_tokens "($amount)"
+    dfx canister call piggybank_backend burn_tokens "($amount, null)"
 }
 
 echo "=== PiggyBank Rewards Simulation ==="
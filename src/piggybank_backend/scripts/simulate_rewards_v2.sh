@@ -25,7 +25,7 @@ apply_rewards() {
 burn_tokens() {
     amount=$1
     echo "Burning $amount tokens..."
-    dfx canister call piggybank_backend burn_tokens "($amount)" || echo "Failed to burn tokens"
+    dfx canister call piggybank_backend burn_tokens "($amount, null)" || echo "Failed to burn tokens"
 }
 
 # Function to display section header
@@ -52,7 +52,7 @@ apply_rewards() {
 # Function to burn tokens
 burn_tokens() {
     amount=$1
-    dfx canister call piggybank_backend burn_tokens "($amount)"
+    dfx canister call piggybank_backend burn_tokens "($amount, null)"
 }
 
 echo "Starting reward simulation..."
@@ -0,0 +1,354 @@
+//! Roles, access guards and the audit log.
+//!
+//! Update methods reject the anonymous principal through
+//! `authenticated_caller`, and privileged endpoints then check
+//! `require_role`. Roles are held per principal and nest: the owner holds
+//! every role and an admin also acts as operator and auditor. Canister
+//! controllers pass every guard so a canister upgraded from before roles
+//! existed stays manageable.
+//!
+//! Every role change and privileged action is appended to the audit log.
+
+use crate::error::Error;
+use crate::fees::FeeOperation;
+use crate::pause::Operation;
+use crate::state::State;
+use crate::{caller, is_controller, time, STATE};
+use candid::{CandidType, Deserialize, Principal};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(CandidType, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Role {
+    /// Manages every role, including other owners.
+    Owner,
+    /// Manages products and rates, and operators and auditors.
+    Admin,
+    /// Runs maturity sweeps.
+    Operator,
+    /// Reads roles and the audit log.
+    Auditor,
+}
+
+impl Role {
+    /// Whether holding `self` also grants `role`.
+    fn includes(self, role: Role) -> bool {
+        match self {
+            Role::Owner => true,
+            Role::Admin => role != Role::Owner,
+            Role::Operator | Role::Auditor => self == role,
+        }
+    }
+}
+
+pub type Roles = BTreeMap<Principal, BTreeSet<Role>>;
+
+/// Principals given roles when the canister is installed. Without init args
+/// the installer becomes the owner.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct InitArgs {
+    pub owner: Principal,
+    pub admins: Vec<Principal>,
+    pub operators: Vec<Principal>,
+    pub auditors: Vec<Principal>,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub enum AuditEvent {
+    RoleGranted { principal: Principal, role: Role },
+    RoleRevoked { principal: Principal, role: Role },
+    ProductAdded { product_id: u64 },
+    ProductUpdated { product_id: u64 },
+    RateSheetPublished { rate_sheet_id: u64 },
+    MaturitySweepRun,
+    Paused { operation: Operation, reason: String },
+    Resumed { operation: Operation },
+    RewardPoolFunded { amount: u64 },
+    FeeScheduleSet { operation: FeeOperation, product_id: Option<u64> },
+    FeeRewardPoolShareSet { share_bps: u32 },
+    PenaltyRewardPoolShareSet { share_bps: u32 },
+    LoyaltyBonusRateSet { bonus_rate_bps: u32 },
+    ReferralSettingsSet,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct AuditEntry {
+    pub id: u64,
+    pub time: u64,
+    pub caller: Principal,
+    pub event: AuditEvent,
+}
+
+pub fn has_role(state: &State, principal: &Principal, role: Role) -> bool {
+    is_controller(principal)
+        || state
+            .roles
+            .get(principal)
+            .is_some_and(|roles| roles.iter().any(|held| held.includes(role)))
+}
+
+/// Fails unless `principal` holds `role`.
+pub fn require_role(state: &State, principal: &Principal, role: Role) -> Result<(), String> {
+    if has_role(state, principal, role) {
+        Ok(())
+    } else {
+        Err(format!("Caller does not have the {:?} role", role))
+    }
+}
+
+/// Appends `event`, done by `caller` now, to the audit log.
+pub fn audit(state: &mut State, caller: Principal, event: AuditEvent) {
+    let id = state.audit_log.len() as u64;
+    state.audit_log.push(AuditEntry {
+        id,
+        time: time(),
+        caller,
+        event,
+    });
+}
+
+/// Gives out the roles in `args`, or makes `installer` the owner.
+pub fn init_roles(state: &mut State, args: Option<InitArgs>, installer: Principal) {
+    let args = args.unwrap_or(InitArgs {
+        owner: installer,
+        admins: Vec::new(),
+        operators: Vec::new(),
+        auditors: Vec::new(),
+    });
+    let grants = std::iter::once((args.owner, Role::Owner))
+        .chain(args.admins.into_iter().map(|p| (p, Role::Admin)))
+        .chain(args.operators.into_iter().map(|p| (p, Role::Operator)))
+        .chain(args.auditors.into_iter().map(|p| (p, Role::Auditor)));
+    for (principal, role) in grants {
+        state.roles.entry(principal).or_default().insert(role);
+        audit(state, installer, AuditEvent::RoleGranted { principal, role });
+    }
+}
+
+/// The caller, unless it is the anonymous principal. Update methods start
+/// with this so that callers who have not logged in cannot act, or share one
+/// account between them.
+pub fn authenticated_caller() -> Result<Principal, Error> {
+    let caller = caller();
+    if caller == Principal::anonymous() {
+        Err(Error::Unauthenticated)
+    } else {
+        Ok(caller)
+    }
+}
+
+/// Only owners manage owners and admins; admins manage the other roles.
+fn role_manager(role: Role) -> Role {
+    match role {
+        Role::Owner | Role::Admin => Role::Owner,
+        Role::Operator | Role::Auditor => Role::Admin,
+    }
+}
+
+#[ic_cdk::update]
+pub fn grant_role(principal: Principal, role: Role) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, role_manager(role))?;
+        if !state.roles.entry(principal).or_default().insert(role) {
+            return Err("Principal already has this role".into());
+        }
+        audit(&mut state, caller, AuditEvent::RoleGranted { principal, role });
+        Ok(())
+    })
+}
+
+#[ic_cdk::update]
+pub fn revoke_role(principal: Principal, role: Role) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, role_manager(role))?;
+        if !state.roles.get(&principal).is_some_and(|roles| roles.contains(&role)) {
+            return Err("Principal does not have this role".into());
+        }
+        if role == Role::Owner {
+            let owners = state.roles.values().filter(|roles| roles.contains(&Role::Owner)).count();
+            if owners == 1 {
+                return Err("Cannot revoke the last owner".into());
+            }
+        }
+        let roles = state.roles.get_mut(&principal).expect("Principal holds the role");
+        roles.remove(&role);
+        if roles.is_empty() {
+            state.roles.remove(&principal);
+        }
+        audit(&mut state, caller, AuditEvent::RoleRevoked { principal, role });
+        Ok(())
+    })
+}
+
+/// Roles held by the caller.
+#[ic_cdk::query]
+pub fn get_my_roles() -> Vec<Role> {
+    let caller = caller();
+    STATE.with(|state| {
+        state
+            .borrow()
+            .roles
+            .get(&caller)
+            .map(|roles| roles.iter().copied().collect())
+            .unwrap_or_default()
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_roles() -> Result<Vec<(Principal, Vec<Role>)>, Error> {
+    let caller = caller();
+    STATE.with(|state| {
+        let state = state.borrow();
+        require_role(&state, &caller, Role::Auditor)?;
+        Ok(state
+            .roles
+            .iter()
+            .map(|(principal, roles)| (*principal, roles.iter().copied().collect()))
+            .collect())
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_audit_log() -> Result<Vec<AuditEntry>, Error> {
+    let caller = caller();
+    STATE.with(|state| {
+        let state = state.borrow();
+        require_role(&state, &caller, Role::Auditor)?;
+        Ok(state.audit_log.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maturity::run_maturity_sweep;
+    use crate::mock::{set_caller, set_controllers, set_time};
+    use crate::products::{add_product, ProductArgs};
+
+    fn principal(id: u8) -> Principal {
+        Principal::from_slice(&[id])
+    }
+
+    fn setup() {
+        STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            *state = State::default();
+            let args = InitArgs {
+                owner: principal(1),
+                admins: vec![principal(2)],
+                operators: vec![principal(3)],
+                auditors: vec![principal(4)],
+            };
+            init_roles(&mut state, Some(args), principal(1));
+        });
+        set_time(0);
+        set_controllers(Vec::new());
+    }
+
+    fn product_args() -> ProductArgs {
+        ProductArgs {
+            name: "1 month".to_string(),
+            duration_nanos: 1_000,
+            interest_rate_bps: 300,
+            early_withdrawal_penalty_bps: 100,
+            min_deposit: 0,
+            max_deposit: None,
+            open: true,
+        }
+    }
+
+    #[test]
+    fn test_init_roles() {
+        setup();
+        set_caller(principal(2));
+        assert_eq!(get_my_roles(), vec![Role::Admin]);
+        set_caller(principal(4));
+        assert_eq!(get_roles().unwrap().len(), 4);
+        assert_eq!(get_audit_log().unwrap().len(), 4);
+
+        STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            *state = State::default();
+            init_roles(&mut state, None, principal(9));
+            assert!(has_role(&state, &principal(9), Role::Owner));
+        });
+    }
+
+    #[test]
+    fn test_guards() {
+        setup();
+        for (caller, allowed) in [(1, true), (2, true), (3, false), (4, false), (5, false)] {
+            set_caller(principal(caller));
+            assert_eq!(add_product(product_args()).is_ok(), allowed);
+        }
+        set_caller(principal(3));
+        assert_eq!(add_product(product_args()).unwrap_err().to_string(), "Caller does not have the Admin role");
+        assert!(get_audit_log().is_err());
+        set_caller(Principal::anonymous());
+        assert_eq!(add_product(product_args()), Err(Error::Unauthenticated));
+        assert_eq!(grant_role(principal(5), Role::Auditor), Err(Error::Unauthenticated));
+
+        // Operators run sweeps, which other principals cannot
+        set_caller(principal(3));
+        run_maturity_sweep().unwrap();
+        set_caller(principal(5));
+        assert_eq!(run_maturity_sweep().unwrap_err().to_string(), "Caller does not have the Operator role");
+
+        // Controllers pass every guard
+        set_controllers(vec![principal(5)]);
+        set_caller(principal(5));
+        assert!(add_product(product_args()).is_ok());
+    }
+
+    #[test]
+    fn test_grant_and_revoke() {
+        setup();
+
+        // Admins manage operators and auditors but not admins
+        set_caller(principal(2));
+        grant_role(principal(5), Role::Operator).unwrap();
+        assert_eq!(grant_role(principal(5), Role::Operator).unwrap_err().to_string(), "Principal already has this role");
+        assert!(grant_role(principal(5), Role::Admin).is_err());
+        revoke_role(principal(5), Role::Operator).unwrap();
+        assert!(revoke_role(principal(5), Role::Operator).is_err());
+
+        // Owners manage everything but cannot remove the last owner
+        set_caller(principal(1));
+        grant_role(principal(5), Role::Admin).unwrap();
+        assert_eq!(revoke_role(principal(1), Role::Owner).unwrap_err().to_string(), "Cannot revoke the last owner");
+        assert_eq!(revoke_role(principal(5), Role::Owner).unwrap_err().to_string(), "Principal does not have this role");
+        grant_role(principal(5), Role::Owner).unwrap();
+        revoke_role(principal(1), Role::Owner).unwrap();
+        assert!(grant_role(principal(6), Role::Auditor).is_err());
+
+        set_caller(principal(4));
+        let events: Vec<AuditEvent> = get_audit_log().unwrap().into_iter().skip(4).map(|e| e.event).collect();
+        assert_eq!(events, vec![
+            AuditEvent::RoleGranted {
+                principal: principal(5),
+                role: Role::Operator
+            },
+            AuditEvent::RoleRevoked {
+                principal: principal(5),
+                role: Role::Operator
+            },
+            AuditEvent::RoleGranted {
+                principal: principal(5),
+                role: Role::Admin
+            },
+            AuditEvent::RoleGranted {
+                principal: principal(5),
+                role: Role::Owner
+            },
+            AuditEvent::RoleRevoked {
+                principal: principal(1),
+                role: Role::Owner
+            },
+        ]);
+    }
+}
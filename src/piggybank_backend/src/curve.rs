@@ -0,0 +1,152 @@
+//! Custom lock durations.
+//!
+//! Instead of picking a product, a user can lock until a date of their
+//! choosing. The deposit is priced from a curve through the lock period
+//! tiers: its rate and penalty are interpolated linearly between the rates
+//! in force for the tiers either side of its lock duration. Durations outside
+//! the shortest and longest tier are not offered.
+
+use crate::error::Error;
+use crate::products::{Catalog, ProductId};
+use crate::{time, DepositTerms, LockPeriod, STATE};
+
+/// Reward index and product ID shared by all custom lock deposits. It is
+/// never assigned to a catalog product.
+pub const CUSTOM_LOCK_PRODUCT_ID: ProductId = ProductId::MAX;
+
+/// Curve points, shortest lock first.
+const TIERS: [LockPeriod; 3] = [LockPeriod::ThreeMonths, LockPeriod::SixMonths, LockPeriod::TwelveMonths];
+
+/// Linear interpolation of `from`..`to` at `x` within `x0`..`x1`, rounded
+/// down or up.
+fn interpolate(x: u64, (x0, from): (u64, u32), (x1, to): (u64, u32), round_up: bool) -> u32 {
+    let span = (x1 - x0) as u128;
+    let offset = (x - x0) as u128;
+    let value = if to >= from {
+        let delta = (to - from) as u128 * offset;
+        from as u128 + if round_up { delta.div_ceil(span) } else { delta / span }
+    } else {
+        let delta = (from - to) as u128 * offset;
+        from as u128 - if round_up { delta / span } else { delta.div_ceil(span) }
+    };
+    value as u32
+}
+
+/// Rate and penalty in force for `tier`'s built-in product at `time`.
+fn tier_rates(catalog: &Catalog, tier: &LockPeriod, time: u64) -> (u32, u32) {
+    match catalog.get(tier.product_id()) {
+        Some(product) => {
+            let rates = catalog.rates_at(product, time);
+            (rates.interest_rate_bps, rates.early_withdrawal_penalty_bps)
+        }
+        None => (tier.interest_rate(), tier.early_withdrawal_penalty()),
+    }
+}
+
+/// Terms for a custom lock of `duration_nanos` opened at `time`. The rate is
+/// rounded down and the penalty up, in line with `money`.
+pub fn custom_terms(catalog: &Catalog, duration_nanos: u64, time: u64) -> Result<DepositTerms, String> {
+    let shortest = TIERS[0].duration_nanos();
+    let longest = TIERS[TIERS.len() - 1].duration_nanos();
+    if duration_nanos < shortest || duration_nanos > longest {
+        return Err("Lock duration is outside the allowed range".to_string());
+    }
+
+    let upper = TIERS
+        .iter()
+        .position(|tier| tier.duration_nanos() >= duration_nanos)
+        .unwrap_or(TIERS.len() - 1);
+    let (interest_rate_bps, early_withdrawal_penalty_bps) = if upper == 0 {
+        tier_rates(catalog, &TIERS[0], time)
+    } else {
+        let (low, high) = (&TIERS[upper - 1], &TIERS[upper]);
+        let (low_rate, low_penalty) = tier_rates(catalog, low, time);
+        let (high_rate, high_penalty) = tier_rates(catalog, high, time);
+        let x0 = low.duration_nanos();
+        let x1 = high.duration_nanos();
+        (
+            interpolate(duration_nanos, (x0, low_rate), (x1, high_rate), false),
+            interpolate(duration_nanos, (x0, low_penalty), (x1, high_penalty), true),
+        )
+    };
+
+    Ok(DepositTerms {
+        product_id: CUSTOM_LOCK_PRODUCT_ID,
+        duration_nanos,
+        interest_rate_bps,
+        early_withdrawal_penalty_bps,
+    })
+}
+
+/// Terms a custom lock until `maturity_time` would be opened with now.
+#[ic_cdk::query]
+pub fn get_custom_lock_terms(maturity_time: u64) -> Result<DepositTerms, Error> {
+    let current_time = time();
+    if maturity_time <= current_time {
+        return Err("Maturity time must be in the future".into());
+    }
+    STATE.with(|state| Ok(custom_terms(&state.borrow().products, maturity_time - current_time, current_time)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{setup, DAY};
+    use crate::mock::set_time;
+    use crate::money::interest;
+    use crate::{get_balance, mint_tokens_until, DepositStatus, MaturityInstruction};
+
+    #[test]
+    fn test_curve_passes_through_tiers() {
+        for tier in TIERS {
+            let terms = custom_terms(&Catalog::default(), tier.duration_nanos(), 0).unwrap();
+            assert_eq!(terms.interest_rate_bps, tier.interest_rate());
+            assert_eq!(terms.early_withdrawal_penalty_bps, tier.early_withdrawal_penalty());
+        }
+    }
+
+    #[test]
+    fn test_curve_interpolates_between_tiers() {
+        // Halfway between 3 months (5%, 2%) and 6 months (7%, 5%)
+        let terms = custom_terms(&Catalog::default(), 135 * DAY, 0).unwrap();
+        assert_eq!(terms.interest_rate_bps, 600);
+        assert_eq!(terms.early_withdrawal_penalty_bps, 350);
+
+        // A day past 6 months: 300 bps / 185 days more rate, rounded down,
+        // and 500 bps / 185 days more penalty, rounded up
+        let terms = custom_terms(&Catalog::default(), 181 * DAY, 0).unwrap();
+        assert_eq!(terms.interest_rate_bps, 701);
+        assert_eq!(terms.early_withdrawal_penalty_bps, 503);
+    }
+
+    #[test]
+    fn test_custom_lock_bounds() {
+        setup();
+        assert!(custom_terms(&Catalog::default(), 89 * DAY, 0).is_err());
+        assert!(custom_terms(&Catalog::default(), 366 * DAY, 0).is_err());
+        assert!(get_custom_lock_terms(0).is_err());
+        assert_eq!(mint_tokens_until(10000, 30 * DAY, None).unwrap_err().to_string(), "Lock duration is outside the allowed range");
+        assert!(get_balance().is_err());
+    }
+
+    #[test]
+    fn test_mint_until_stores_terms() {
+        setup();
+        set_time(DAY);
+        let maturity_time = 136 * DAY;
+        let principal = mint_tokens_until(10000, maturity_time, Some(MaturityInstruction::Renew)).unwrap().deposited;
+
+        let deposit = get_balance().unwrap().deposits[0].clone();
+        assert_eq!(deposit.product_id, CUSTOM_LOCK_PRODUCT_ID);
+        assert_eq!(deposit.lock_end_time(), maturity_time);
+        assert_eq!(deposit.interest_rate_bps, 600);
+        assert_eq!(deposit.early_withdrawal_penalty_bps, 350);
+
+        // Renewing keeps the custom duration and pays the interpolated rate
+        set_time(maturity_time);
+        let deposit = get_balance().unwrap().deposits[0].clone();
+        assert_eq!(deposit.status, DepositStatus::Locked);
+        assert_eq!(deposit.lock_end_time(), maturity_time + 135 * DAY);
+        assert_eq!(deposit.amount, principal + interest(principal, 600, 135 * DAY));
+    }
+}
@@ -0,0 +1,41 @@
+//! The error type returned by endpoints.
+//!
+//! Failures a client is expected to handle on their own get a variant;
+//! everything else is `Rejected` with a message. Internal helpers return
+//! `String` errors, which `?` turns into `Rejected`.
+
+use crate::pause::Operation;
+use candid::{CandidType, Deserialize};
+use std::fmt;
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub enum Error {
+    /// The caller is the anonymous principal and must log in first.
+    Unauthenticated,
+    /// An admin has paused the operation, for the reason given.
+    Paused { operation: Operation, reason: String },
+    /// The request was refused for the reason given.
+    Rejected(String),
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Rejected(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Rejected(message.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unauthenticated => write!(f, "Caller is not authenticated"),
+            Error::Paused { operation, reason } => write!(f, "{:?} is paused: {}", operation, reason),
+            Error::Rejected(message) => write!(f, "{}", message),
+        }
+    }
+}
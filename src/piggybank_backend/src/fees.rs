@@ -0,0 +1,310 @@
+//! Transaction fees.
+//!
+//! Admins set a fee schedule per operation, and may override it for single
+//! products. A fee is a percentage of the amount, rounded up like a penalty
+//! and then held between the schedule's minimum and maximum. Without a
+//! schedule nothing is charged.
+//!
+//! Fees are paid into the fee treasury, which passes an admin-set share of
+//! every fee on to the reward pool.
+
+use crate::access::{audit, authenticated_caller, require_role, AuditEvent, Role};
+use crate::error::Error;
+use crate::money::{penalty, share, BASIS_POINTS};
+use crate::products::ProductId;
+use crate::state::State;
+use crate::{caller, STATE};
+use candid::{CandidType, Deserialize};
+use std::collections::BTreeMap;
+
+#[derive(CandidType, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum FeeOperation {
+    /// Charged on the tokens minted, before they are locked.
+    Mint,
+    /// Charged on the amount withdrawn, on top of any penalty.
+    Burn,
+}
+
+/// Minimum and maximum are in token minor units.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct FeeSchedule {
+    pub rate_bps: u32,
+    pub min_fee: u64,
+    pub max_fee: Option<u64>,
+}
+
+impl FeeSchedule {
+    /// Fee on `amount`, never more than `amount` itself.
+    fn fee(&self, amount: u64) -> u64 {
+        let fee = penalty(amount, self.rate_bps).max(self.min_fee);
+        let fee = self.max_fee.map_or(fee, |max_fee| fee.min(max_fee));
+        fee.min(amount)
+    }
+}
+
+/// Fees collected, in token minor units.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct FeeTreasury {
+    /// Fees held after the reward pool's share.
+    pub balance: u64,
+    pub mint_fees: u64,
+    pub burn_fees: u64,
+    /// Passed on to the reward pool.
+    pub to_reward_pool: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct FeeScheduleEntry {
+    pub operation: FeeOperation,
+    /// The product the schedule applies to, or every product without a
+    /// schedule of its own.
+    pub product_id: Option<ProductId>,
+    pub schedule: FeeSchedule,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct FeeSettings {
+    pub schedules: Vec<FeeScheduleEntry>,
+    pub reward_pool_share_bps: u32,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Fees {
+    schedules: BTreeMap<(FeeOperation, Option<ProductId>), FeeSchedule>,
+    reward_pool_share_bps: u32,
+    pub treasury: FeeTreasury,
+}
+
+impl Fees {
+    /// Fee for `operation` on `amount` of `product`.
+    pub fn fee(&self, operation: FeeOperation, product_id: ProductId, amount: u64) -> u64 {
+        self.schedules
+            .get(&(operation, Some(product_id)))
+            .or_else(|| self.schedules.get(&(operation, None)))
+            .map_or(0, |schedule| schedule.fee(amount))
+    }
+}
+
+/// Books `fee` charged on `operation` and passes the reward pool its share,
+/// rounded down.
+pub fn collect(state: &mut State, operation: FeeOperation, fee: u64) {
+    let fees = &mut state.fees;
+    let to_reward_pool = share(fee, fees.reward_pool_share_bps);
+    let treasury = &mut fees.treasury;
+    treasury.balance += fee - to_reward_pool;
+    treasury.to_reward_pool += to_reward_pool;
+    match operation {
+        FeeOperation::Mint => treasury.mint_fees += fee,
+        FeeOperation::Burn => treasury.burn_fees += fee,
+    }
+    state.reward_pool.credit_fees(to_reward_pool);
+}
+
+/// Sets or, with `None`, removes the schedule for `operation`, for one
+/// product or for all of them.
+#[ic_cdk::update]
+pub fn set_fee_schedule(
+    operation: FeeOperation,
+    product_id: Option<ProductId>,
+    schedule: Option<FeeSchedule>,
+) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+    if let Some(schedule) = &schedule {
+        if schedule.rate_bps as u64 > BASIS_POINTS {
+            return Err("Rates must not exceed 10000 basis points".into());
+        }
+        if schedule.max_fee.is_some_and(|max_fee| max_fee < schedule.min_fee) {
+            return Err("Maximum fee is below the minimum".into());
+        }
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        if product_id.is_some_and(|id| state.products.get(id).is_none()) {
+            return Err("Product not found".into());
+        }
+        let key = (operation, product_id);
+        match schedule {
+            Some(schedule) => state.fees.schedules.insert(key, schedule),
+            None => state.fees.schedules.remove(&key),
+        };
+        audit(&mut state, caller, AuditEvent::FeeScheduleSet { operation, product_id });
+        Ok(())
+    })
+}
+
+/// Sets the share of every fee passed on to the reward pool.
+#[ic_cdk::update]
+pub fn set_fee_reward_pool_share(share_bps: u32) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+    if share_bps as u64 > BASIS_POINTS {
+        return Err("Share must not exceed 10000 basis points".into());
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        state.fees.reward_pool_share_bps = share_bps;
+        audit(&mut state, caller, AuditEvent::FeeRewardPoolShareSet { share_bps });
+        Ok(())
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_fee_settings() -> FeeSettings {
+    STATE.with(|state| {
+        let fees = &state.borrow().fees;
+        FeeSettings {
+            schedules: fees
+                .schedules
+                .iter()
+                .map(|(&(operation, product_id), schedule)| FeeScheduleEntry {
+                    operation,
+                    product_id,
+                    schedule: schedule.clone(),
+                })
+                .collect(),
+            reward_pool_share_bps: fees.reward_pool_share_bps,
+        }
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_fee_treasury() -> Result<FeeTreasury, Error> {
+    let caller = caller();
+    STATE.with(|state| {
+        let state = state.borrow();
+        require_role(&state, &caller, Role::Auditor)?;
+        Ok(state.fees.treasury.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, admin, user, DAY};
+    use crate::mock::set_caller;
+    use crate::money::penalty;
+    use crate::withdrawal::{quote_withdrawal, WithdrawalStrategy};
+    use crate::{burn_tokens, get_balance, mint_tokens, mint_tokens_until, withdraw_deposit, LockPeriod};
+
+    fn setup() {
+        fixtures::setup();
+        set_caller(admin());
+    }
+
+    fn schedule(rate_bps: u32, min_fee: u64, max_fee: Option<u64>) -> Option<FeeSchedule> {
+        Some(FeeSchedule {
+            rate_bps,
+            min_fee,
+            max_fee,
+        })
+    }
+
+    #[test]
+    fn test_schedule_caps() {
+        let capped = schedule(100, 500, Some(2_000)).unwrap();
+        assert_eq!(capped.fee(100_000), 1_000);
+        assert_eq!(capped.fee(1_000), 500);
+        assert_eq!(capped.fee(1_000_000), 2_000);
+        assert_eq!(capped.fee(300), 300);
+        // The percentage rounds up
+        assert_eq!(schedule(100, 0, None).unwrap().fee(150), 2);
+    }
+
+    #[test]
+    fn test_mint_fee() {
+        setup();
+        set_fee_schedule(FeeOperation::Mint, None, schedule(100, 0, None)).unwrap();
+        set_fee_schedule(FeeOperation::Mint, Some(LockPeriod::TwelveMonths.product_id()), schedule(200, 0, None))
+            .unwrap();
+        set_caller(user());
+
+        let result = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        assert_eq!(result.fee, penalty(result.token_amount, 100));
+        assert_eq!(result.deposited, result.token_amount - result.fee);
+        assert_eq!(get_balance().unwrap().deposits[0].amount, result.deposited);
+
+        // The product's own schedule overrides the default; custom locks
+        // use the default
+        let twelve_month = mint_tokens(10000, LockPeriod::TwelveMonths.into(), None).unwrap();
+        assert_eq!(twelve_month.fee, penalty(twelve_month.token_amount, 200));
+        let custom = mint_tokens_until(10000, 100 * DAY, None).unwrap();
+        assert_eq!(custom.fee, result.fee);
+
+        set_caller(admin());
+        let treasury = get_fee_treasury().unwrap();
+        assert_eq!(treasury.mint_fees, result.fee * 2 + twelve_month.fee);
+        assert_eq!(treasury.balance, treasury.mint_fees);
+    }
+
+    #[test]
+    fn test_burn_fee_and_reward_pool_share() {
+        setup();
+        set_fee_schedule(FeeOperation::Burn, None, schedule(100, 0, Some(1_000_000))).unwrap();
+        set_fee_reward_pool_share(2_500).unwrap();
+        let pool_before = STATE.with(|state| state.borrow().reward_pool.balance);
+        set_caller(user());
+
+        let deposited = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        let quote = burn_tokens(deposited / 100, None).unwrap();
+        assert_eq!(quote.total_fee, penalty(deposited / 100, 100));
+        assert_eq!(quote.net_payout, deposited / 100 - quote.total_penalty - quote.total_fee);
+
+        // The maximum fee applies
+        let deposit_id = get_balance().unwrap().deposits[0].id;
+        let quote_large = withdraw_deposit(deposit_id, deposited - deposited / 100).unwrap();
+        assert_eq!(quote_large.total_fee, 1_000_000);
+
+        let fees = quote.total_fee + quote_large.total_fee;
+        set_caller(admin());
+        let treasury = get_fee_treasury().unwrap();
+        assert_eq!(treasury.burn_fees, fees);
+        assert_eq!(treasury.to_reward_pool, fees / 4);
+        assert_eq!(treasury.balance, fees - fees / 4);
+        let pool = STATE.with(|state| state.borrow().reward_pool.clone());
+        assert_eq!(pool.fees, fees / 4);
+        assert_eq!(pool.balance, pool_before + fees / 4);
+    }
+
+    #[test]
+    fn test_least_penalty_counts_fees() {
+        setup();
+        set_fee_schedule(FeeOperation::Burn, Some(LockPeriod::ThreeMonths.product_id()), schedule(1_500, 0, None))
+            .unwrap();
+        set_caller(user());
+        let three_month = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        mint_tokens(10000, LockPeriod::TwelveMonths.into(), None).unwrap();
+
+        // The three-month penalty is lower, but its fee makes it costlier
+        let balance = get_balance().unwrap();
+        let quote = quote_withdrawal(three_month, Some(WithdrawalStrategy::LeastPenalty)).unwrap();
+        assert_eq!(quote.legs.len(), 1);
+        assert_eq!(quote.legs[0].deposit_id, balance.deposits[1].id);
+        assert_eq!(quote.total_fee, 0);
+    }
+
+    #[test]
+    fn test_fee_settings_errors() {
+        setup();
+        assert!(set_fee_schedule(FeeOperation::Mint, Some(7), schedule(100, 0, None)).is_err());
+        assert!(set_fee_schedule(FeeOperation::Mint, None, schedule(10_001, 0, None)).is_err());
+        assert!(set_fee_schedule(FeeOperation::Mint, None, schedule(100, 10, Some(5))).is_err());
+        assert!(set_fee_reward_pool_share(10_001).is_err());
+
+        set_fee_schedule(FeeOperation::Mint, None, schedule(0, 1_000_000_000, None)).unwrap();
+        assert_eq!(get_fee_settings().schedules.len(), 1);
+        set_caller(user());
+        assert_eq!(
+            mint_tokens(1, LockPeriod::ThreeMonths.into(), None).unwrap_err().to_string(),
+            "Amount does not cover the fee"
+        );
+        assert!(set_fee_schedule(FeeOperation::Mint, None, None).is_err());
+        assert!(get_fee_treasury().is_err());
+
+        set_caller(admin());
+        set_fee_schedule(FeeOperation::Mint, None, None).unwrap();
+        assert!(get_fee_settings().schedules.is_empty());
+    }
+}
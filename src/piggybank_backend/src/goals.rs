@@ -0,0 +1,309 @@
+//! Savings goals.
+//!
+//! A goal is a target amount a user saves towards, optionally by a deadline.
+//! Saving into a goal opens an ordinary locked deposit in the goal's product,
+//! exactly as `mint_tokens` does, and counts the tokens locked after fees
+//! towards the goal. A goal is completed once it reaches its target, and
+//! withdrawals from its deposits count against it, reopening it if they take
+//! it back below the target. Owners are notified as their goals pass
+//! milestones and when they are completed.
+//!
+//! Only registered users can create goals.
+
+use crate::error::Error;
+use crate::products::ProductRef;
+use crate::state::State;
+use crate::{access, caller, open_deposit, time, users, STATE};
+use candid::{CandidType, Deserialize, Principal};
+
+#[derive(CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GoalStatus {
+    Active,
+    Completed,
+}
+
+/// Amounts are in token minor units.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct SavingsGoal {
+    pub id: u64,
+    pub user_id: Principal,
+    pub name: String,
+    pub category: String,
+    pub target_amount: u64,
+    pub current_amount: u64,
+    pub deadline: Option<u64>,
+    /// The product deposits into the goal are locked in.
+    pub product: ProductRef,
+    pub status: GoalStatus,
+    /// Deposits opened by saving into the goal.
+    pub deposit_ids: Vec<u64>,
+    pub created_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CreateGoalArgs {
+    pub name: String,
+    /// In token minor units.
+    pub target_amount: u64,
+    pub category: String,
+    pub deadline: Option<u64>,
+    /// Defaults to the three-month product.
+    pub product: Option<ProductRef>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DepositArgs {
+    pub goal_id: u64,
+    /// In whole rupees, as for `mint_tokens`.
+    pub amount: u64,
+}
+
+#[ic_cdk::update]
+pub fn create_savings_goal(args: CreateGoalArgs) -> Result<SavingsGoal, Error> {
+    let caller = access::authenticated_caller()?;
+    let current_time = time();
+    if args.name.trim().is_empty() {
+        return Err("Goal name must not be empty".into());
+    }
+    if args.target_amount == 0 {
+        return Err("Target amount must be greater than 0".into());
+    }
+    if args.deadline.is_some_and(|deadline| deadline <= current_time) {
+        return Err("Deadline must be in the future".into());
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        users::require_registered(&state, &caller)?;
+        let product = args.product.unwrap_or(ProductRef::ThreeMonths);
+        if state.products.get(product.id()).is_none() {
+            return Err("Product not found".into());
+        }
+        let goal = SavingsGoal {
+            id: state.next_goal_id,
+            user_id: caller,
+            name: args.name,
+            category: args.category,
+            target_amount: args.target_amount,
+            current_amount: 0,
+            deadline: args.deadline,
+            product,
+            status: GoalStatus::Active,
+            deposit_ids: Vec::new(),
+            created_at: current_time,
+        };
+        state.next_goal_id += 1;
+        state.savings_goals.insert(goal.id, goal.clone());
+        Ok(goal)
+    })
+}
+
+/// Locks `amount` rupees in the goal's product and counts the tokens locked
+/// towards the goal.
+#[ic_cdk::update]
+pub fn deposit(args: DepositArgs) -> Result<SavingsGoal, Error> {
+    let caller = access::authenticated_caller()?;
+    let current_time = time();
+
+    open_deposit(args.amount, None, Some(args.goal_id), |state, token_amount| {
+        let goal = state
+            .savings_goals
+            .get(&args.goal_id)
+            .filter(|goal| goal.user_id == caller)
+            .ok_or("Goal not found")?;
+        if goal.status == GoalStatus::Completed {
+            return Err("Goal is already completed".to_string());
+        }
+        state.products.terms_for_deposit(goal.product.id(), token_amount, current_time)
+    })?;
+
+    Ok(STATE.with(|state| state.borrow().savings_goals[&args.goal_id].clone()))
+}
+
+/// Counts `amount` towards the goal, completing it at its target, and
+/// notifies the owner of the progress made. Leaves the goal untouched if it
+/// fails.
+pub fn contribute(state: &mut State, goal_id: u64, amount: u64, current_time: u64) -> Result<&mut SavingsGoal, String> {
+    let goal = state.savings_goals.get_mut(&goal_id).ok_or("Goal not found")?;
+    let previous_amount = goal.current_amount;
+    goal.current_amount = goal.current_amount.checked_add(amount).ok_or("Amount too large")?;
+    if goal.current_amount >= goal.target_amount {
+        goal.status = GoalStatus::Completed;
+    }
+    state.notifications.goal_progress(goal, previous_amount, current_time);
+    Ok(goal)
+}
+
+/// Takes `amount` withdrawn from `deposit_id` off the progress of the goal it
+/// was saved into, if any, reopening it if it falls below its target, and
+/// returns that goal.
+pub fn withdraw(state: &mut State, user: Principal, deposit_id: u64, amount: u64) -> Option<u64> {
+    let goal = state
+        .savings_goals
+        .values_mut()
+        .find(|goal| goal.user_id == user && goal.deposit_ids.contains(&deposit_id))?;
+    goal.current_amount = goal.current_amount.saturating_sub(amount);
+    if goal.current_amount < goal.target_amount {
+        goal.status = GoalStatus::Active;
+    }
+    Some(goal.id)
+}
+
+/// One of the caller's goals.
+#[ic_cdk::query]
+pub fn get_savings_goal(goal_id: u64) -> Option<SavingsGoal> {
+    let caller = caller();
+    STATE.with(|state| {
+        state
+            .borrow()
+            .savings_goals
+            .get(&goal_id)
+            .filter(|goal| goal.user_id == caller)
+            .cloned()
+    })
+}
+
+/// The caller's goals, oldest first.
+#[ic_cdk::query]
+pub fn list_savings_goals() -> Vec<SavingsGoal> {
+    let caller = caller();
+    STATE.with(|state| {
+        state
+            .borrow()
+            .savings_goals
+            .values()
+            .filter(|goal| goal.user_id == caller)
+            .cloned()
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, other_user, user, DAY};
+    use crate::mock::{set_caller, set_time};
+    use crate::money::inr_to_tokens;
+    use crate::users::register_user;
+    use crate::{burn_tokens, get_balance, LockPeriod};
+
+    fn setup() {
+        fixtures::setup();
+        set_time(DAY);
+        register_user("saver".to_string(), "saver@example.com".to_string()).unwrap();
+    }
+
+    fn goal_args(target_amount: u64, deadline: Option<u64>) -> CreateGoalArgs {
+        CreateGoalArgs {
+            name: "Holiday".to_string(),
+            target_amount,
+            category: "Travel".to_string(),
+            deadline,
+            product: None,
+        }
+    }
+
+    #[test]
+    fn test_deposits_fill_goal() {
+        setup();
+        let goal = create_savings_goal(goal_args(inr_to_tokens(10000).unwrap(), Some(30 * DAY))).unwrap();
+        assert_eq!(goal.status, GoalStatus::Active);
+        assert_eq!(goal.user_id, user());
+
+        let goal = deposit(DepositArgs {
+            goal_id: goal.id,
+            amount: 7500,
+        })
+        .unwrap();
+        assert_eq!(goal.current_amount, inr_to_tokens(7500).unwrap());
+        assert_eq!(goal.status, GoalStatus::Active);
+
+        // Goal deposits are ordinary locked deposits
+        let balance = get_balance().unwrap();
+        assert_eq!(balance.locked_balance, goal.current_amount);
+        assert_eq!(balance.deposits[0].id, goal.deposit_ids[0]);
+        assert_eq!(balance.deposits[0].product_id, LockPeriod::ThreeMonths.product_id());
+
+        let goal = deposit(DepositArgs {
+            goal_id: goal.id,
+            amount: 2500,
+        })
+        .unwrap();
+        assert_eq!(goal.status, GoalStatus::Completed);
+        assert_eq!(get_savings_goal(goal.id), Some(goal.clone()));
+        assert_eq!(
+            deposit(DepositArgs { goal_id: goal.id, amount: 1 }).unwrap_err().to_string(),
+            "Goal is already completed"
+        );
+    }
+
+    #[test]
+    fn test_create_goal_errors() {
+        setup();
+        assert_eq!(
+            create_savings_goal(goal_args(1_000, Some(DAY - 1))).unwrap_err().to_string(),
+            "Deadline must be in the future"
+        );
+        assert!(create_savings_goal(goal_args(0, None)).is_err());
+        let mut args = goal_args(1_000, None);
+        args.product = Some(ProductRef::Id(7));
+        assert_eq!(create_savings_goal(args).unwrap_err().to_string(), "Product not found");
+        let mut args = goal_args(1_000, None);
+        args.name = " ".to_string();
+        assert_eq!(create_savings_goal(args).unwrap_err().to_string(), "Goal name must not be empty");
+
+        set_caller(Principal::anonymous());
+        assert_eq!(create_savings_goal(goal_args(1_000, None)).unwrap_err(), Error::Unauthenticated);
+        assert!(list_savings_goals().is_empty());
+
+        set_caller(other_user());
+        assert_eq!(create_savings_goal(goal_args(1_000, None)).unwrap_err().to_string(), "User not registered");
+    }
+
+    #[test]
+    fn test_goals_are_private() {
+        setup();
+        let goal = create_savings_goal(goal_args(1_000, None)).unwrap();
+        assert_eq!(list_savings_goals().len(), 1);
+
+        assert_eq!(get_savings_goal(goal.id + 1), None);
+
+        set_caller(other_user());
+        assert_eq!(get_savings_goal(goal.id), None);
+        assert!(list_savings_goals().is_empty());
+        assert_eq!(
+            deposit(DepositArgs { goal_id: goal.id, amount: 100 }).unwrap_err().to_string(),
+            "Goal not found"
+        );
+        assert!(get_balance().is_err());
+    }
+
+    #[test]
+    fn test_withdrawal_reopens_goal() {
+        setup();
+        let goal = create_savings_goal(goal_args(inr_to_tokens(1000).unwrap(), None)).unwrap();
+        let goal = deposit(DepositArgs { goal_id: goal.id, amount: 1000 }).unwrap();
+        assert_eq!(goal.status, GoalStatus::Completed);
+
+        burn_tokens(1_000_000, None).unwrap();
+        let goal = get_savings_goal(goal.id).unwrap();
+        assert_eq!(goal.status, GoalStatus::Active);
+        assert_eq!(goal.current_amount, goal.target_amount - 1_000_000);
+        deposit(DepositArgs { goal_id: goal.id, amount: 100 }).unwrap();
+    }
+
+    #[test]
+    fn test_failed_contribution_opens_no_deposit() {
+        setup();
+        let goal = create_savings_goal(goal_args(u64::MAX, None)).unwrap();
+        STATE.with(|state| state.borrow_mut().savings_goals.get_mut(&goal.id).unwrap().current_amount = u64::MAX - 1);
+
+        assert_eq!(
+            deposit(DepositArgs { goal_id: goal.id, amount: 100 }).unwrap_err().to_string(),
+            "Amount too large"
+        );
+        assert!(get_balance().is_err());
+        assert!(STATE.with(|state| state.borrow().transactions.is_empty()));
+        assert_eq!(get_savings_goal(goal.id), Some(SavingsGoal { current_amount: u64::MAX - 1, ..goal }));
+    }
+}
@@ -0,0 +1,149 @@
+//! Loyalty bonuses.
+//!
+//! A deposit that completes a term without any early withdrawal earns a
+//! bonus on top of its interest, at an admin-set annual rate over the term.
+//! Bonuses are drawn from the reward pool like interest but recorded apart
+//! from it, in `loyalty_bonus_earned`. Each user also keeps a streak of terms
+//! completed in a row, which any early withdrawal resets.
+
+use crate::access::{audit, authenticated_caller, require_role, AuditEvent, Role};
+use crate::error::Error;
+use crate::money::{interest_with_carry, BASIS_POINTS};
+use crate::{TokenDeposit, STATE};
+use candid::{CandidType, Deserialize};
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct LoyaltySettings {
+    /// Annual bonus rate in basis points, earned over a completed term.
+    pub bonus_rate_bps: u32,
+}
+
+impl LoyaltySettings {
+    /// Bonus earned by `deposit` for completing its current term, or nothing
+    /// if it was withdrawn from early.
+    pub fn bonus(&self, deposit: &TokenDeposit) -> u64 {
+        if deposit.partially_withdrawn {
+            return 0;
+        }
+        interest_with_carry(deposit.amount, self.bonus_rate_bps, deposit.lock_duration_nanos, 0).0
+    }
+}
+
+#[ic_cdk::update]
+pub fn set_loyalty_bonus_rate(bonus_rate_bps: u32) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+    if bonus_rate_bps as u64 > BASIS_POINTS {
+        return Err("Rates must not exceed 10000 basis points".into());
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        state.loyalty.bonus_rate_bps = bonus_rate_bps;
+        audit(&mut state, caller, AuditEvent::LoyaltyBonusRateSet { bonus_rate_bps });
+        Ok(())
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_loyalty_settings() -> LoyaltySettings {
+    STATE.with(|state| state.borrow().loyalty.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, admin, as_admin, DAY};
+    use crate::pause::{pause_operation, resume_operation, Operation};
+    use crate::mock::{set_caller, set_time};
+    use crate::money::interest;
+    use crate::{apply_rewards, get_balance, mint_tokens, withdraw_deposit, LockPeriod, MaturityInstruction};
+
+    fn setup() {
+        fixtures::setup();
+        as_admin(|| set_loyalty_bonus_rate(100).unwrap());
+    }
+
+    #[test]
+    fn test_bonus_for_completed_terms() {
+        setup();
+        let term = LockPeriod::ThreeMonths.duration_nanos();
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), Some(MaturityInstruction::Renew))
+            .unwrap()
+            .deposited;
+
+        set_time(term);
+        let first_interest = interest(principal, 500, term);
+        let first_bonus = interest(principal, 100, term);
+        let balance = get_balance().unwrap();
+        assert_eq!(balance.rewards_earned, first_interest);
+        assert_eq!(balance.loyalty_bonus_earned, first_bonus);
+        assert_eq!(balance.loyalty_streak, 1);
+        // The bonus is renewed with the interest
+        assert_eq!(balance.deposits[0].amount, principal + first_interest + first_bonus);
+
+        set_time(2 * term);
+        let balance = get_balance().unwrap();
+        assert_eq!(balance.loyalty_streak, 2);
+        assert!(balance.loyalty_bonus_earned > 2 * first_bonus);
+
+        // Bonuses are drawn from the pool apart from interest
+        apply_rewards().unwrap();
+        let pool = STATE.with(|state| state.borrow().reward_pool.clone());
+        assert_eq!(pool.loyalty_bonuses, balance.loyalty_bonus_earned);
+        assert_eq!(pool.interest_paid, balance.rewards_earned);
+    }
+
+    #[test]
+    fn test_no_bonus_while_rewards_paused() {
+        setup();
+        let term = LockPeriod::ThreeMonths.duration_nanos();
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), Some(MaturityInstruction::Renew)).unwrap();
+
+        // A term completed before the pause still earns its bonus
+        set_time(term + 10 * DAY);
+        as_admin(|| pause_operation(Operation::Rewards, "Incident".to_string()).unwrap());
+        let bonus = get_balance().unwrap().loyalty_bonus_earned;
+        assert!(bonus > 0);
+
+        // One completed while paused does not
+        set_time(2 * term + 10 * DAY);
+        as_admin(|| resume_operation(Operation::Rewards).unwrap());
+        let balance = get_balance().unwrap();
+        assert_eq!(balance.loyalty_bonus_earned, bonus);
+        assert_eq!(balance.loyalty_streak, 2);
+    }
+
+    #[test]
+    fn test_early_withdrawal_forfeits_bonus_and_streak() {
+        setup();
+        let term = LockPeriod::ThreeMonths.duration_nanos();
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), Some(MaturityInstruction::Renew)).unwrap();
+        set_time(term);
+        assert_eq!(get_balance().unwrap().loyalty_streak, 1);
+
+        let bonus_so_far = get_balance().unwrap().loyalty_bonus_earned;
+        withdraw_deposit(0, 1_000).unwrap();
+        assert_eq!(get_balance().unwrap().loyalty_streak, 0);
+
+        // The term withdrawn from earns no bonus; the one after does again
+        set_time(2 * term);
+        let balance = get_balance().unwrap();
+        assert_eq!(balance.loyalty_bonus_earned, bonus_so_far);
+        assert_eq!(balance.loyalty_streak, 0);
+        set_time(3 * term);
+        let balance = get_balance().unwrap();
+        assert!(balance.loyalty_bonus_earned > bonus_so_far);
+        assert_eq!(balance.loyalty_streak, 1);
+    }
+
+    #[test]
+    fn test_set_loyalty_bonus_rate() {
+        setup();
+        assert_eq!(set_loyalty_bonus_rate(200).unwrap_err().to_string(), "Caller does not have the Admin role");
+        set_caller(admin());
+        assert!(set_loyalty_bonus_rate(10_001).is_err());
+        set_loyalty_bonus_rate(0).unwrap();
+        assert_eq!(get_loyalty_settings().bonus_rate_bps, 0);
+    }
+}
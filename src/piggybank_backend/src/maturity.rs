@@ -0,0 +1,419 @@
+//! Accrues interest on locked deposits, drawn from the reward pool, and
+//! processes deposits whose lock period has ended: the term's accrued
+//! interest is paid and the deposit is either moved from `locked_balance`
+//! into `available_balance` or rolled into a new term, as its
+//! `MaturityInstruction` asks.
+//!
+//! Balances are refreshed whenever they are read or updated, and all balances
+//! are swept periodically by a timer so the stored state does not drift.
+//! Operators can also run a sweep on demand.
+
+use crate::access::{audit, authenticated_caller, require_role, AuditEvent, Role};
+use crate::curve::{custom_terms, CUSTOM_LOCK_PRODUCT_ID};
+use crate::error::Error;
+use crate::loyalty::LoyaltySettings;
+use crate::products::{Catalog, ProductId, ProductRef};
+use crate::rewards::{reward_index, RewardIndices};
+use crate::state::State;
+use crate::transactions;
+use crate::treasury::RewardPool;
+use crate::{time, DepositStatus, DepositTerms, TokenDeposit, UserBalance, STATE};
+use candid::{CandidType, Deserialize, Principal};
+use std::time::Duration;
+
+const MATURITY_SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// What happens to a deposit when its lock period ends.
+///
+/// A renewal is opened like a new deposit on the product's terms at the time.
+/// If the product is closed or will not take the amount, the deposit is paid
+/// out instead.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub enum MaturityInstruction {
+    /// Unlock the deposit into `available_balance`.
+    #[default]
+    PayOut,
+    /// Lock principal and interest again in the same product.
+    Renew,
+    /// Lock principal and interest again in a different product.
+    RenewAs(ProductRef),
+    /// Lock the principal again in the same product and pay the interest
+    /// into `available_balance`.
+    RenewPrincipal,
+}
+
+/// Rejects instructions that renew into a product that does not exist.
+pub fn validate_instruction(instruction: &MaturityInstruction, catalog: &Catalog) -> Result<(), String> {
+    match instruction {
+        MaturityInstruction::RenewAs(product) if catalog.get(product.id()).is_none() => {
+            Err("Product not found".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Terms for renewing `amount` of `deposit` into `product_id` at
+/// `renewal_time`, if that product takes it. Custom locks renew for the same
+/// duration.
+fn renewal_terms(
+    deposit: &TokenDeposit,
+    product_id: ProductId,
+    amount: u64,
+    catalog: &Catalog,
+    renewal_time: u64,
+) -> Option<DepositTerms> {
+    if product_id == CUSTOM_LOCK_PRODUCT_ID {
+        custom_terms(catalog, deposit.lock_duration_nanos, renewal_time).ok()
+    } else {
+        catalog.terms_for_deposit(product_id, amount, renewal_time).ok()
+    }
+}
+
+/// Starts a new term on `deposit` at `renewal_time` on `terms`.
+fn renew(deposit: &mut TokenDeposit, terms: DepositTerms, indices: &RewardIndices, renewal_time: u64) {
+    deposit.reward_index = reward_index(indices, terms.product_id).value_at(renewal_time);
+    deposit.product_id = terms.product_id;
+    deposit.lock_duration_nanos = terms.duration_nanos;
+    deposit.interest_rate_bps = terms.interest_rate_bps;
+    deposit.early_withdrawal_penalty_bps = terms.early_withdrawal_penalty_bps;
+    deposit.deposit_time = renewal_time;
+    deposit.last_accrual_time = renewal_time;
+}
+
+/// A term that ended in a refresh and what it paid.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CompletedTerm {
+    pub deposit_id: u64,
+    pub ended_at: u64,
+    pub interest: u64,
+    pub loyalty_bonus: u64,
+}
+
+/// What a refresh accrued and paid.
+#[derive(Default)]
+pub struct Refresh {
+    /// Interest accrued, including on terms that ended.
+    pub accrued: u64,
+    pub completed: Vec<CompletedTerm>,
+}
+
+/// Processes every locked deposit past its lock end and accrues interest on
+/// the rest up to `current_time`.
+///
+/// A matured deposit is accrued up to its lock end and the term's interest,
+/// with any loyalty bonus, is paid: deposits to be paid out are marked
+/// matured and their value moved to `available_balance`; deposits to be
+/// renewed start a new term from their lock end, as many times as needed to
+/// catch up. No bonus is paid for a term processed while the deposit's reward
+/// index is frozen, that is while rewards are paused.
+pub fn refresh_deposits(
+    balance: &mut UserBalance,
+    next_deposit_id: &mut u64,
+    catalog: &Catalog,
+    indices: &RewardIndices,
+    pool: &mut RewardPool,
+    loyalty: &LoyaltySettings,
+    current_time: u64,
+) -> Refresh {
+    let mut refresh = Refresh::default();
+    let mut payouts = Vec::new();
+    for deposit in &mut balance.deposits {
+        while deposit.status == DepositStatus::Locked && current_time >= deposit.lock_end_time() {
+            let lock_end_time = deposit.lock_end_time();
+            let index = reward_index(indices, deposit.product_id);
+            refresh.accrued += deposit.accrue(&index, pool, lock_end_time);
+            let term_interest = std::mem::take(&mut deposit.accrued_interest);
+            deposit.accrual_remainder = 0;
+            balance.rewards_earned += term_interest;
+            let bonus = if index.paused { 0 } else { pool.draw_loyalty_bonus(loyalty.bonus(deposit)) };
+            balance.loyalty_bonus_earned += bonus;
+            if deposit.partially_withdrawn {
+                deposit.partially_withdrawn = false;
+            } else {
+                balance.loyalty_streak += 1;
+            }
+            let term_earnings = term_interest + bonus;
+            balance.total_balance += term_earnings;
+            refresh.completed.push(CompletedTerm {
+                deposit_id: deposit.id,
+                ended_at: lock_end_time,
+                interest: term_interest,
+                loyalty_bonus: bonus,
+            });
+
+            let instruction = deposit.maturity_instruction.clone();
+            let (product_id, renewal_amount) = match &instruction {
+                MaturityInstruction::PayOut => (None, 0),
+                MaturityInstruction::Renew => (Some(deposit.product_id), deposit.amount + term_earnings),
+                MaturityInstruction::RenewAs(product) => (Some(product.id()), deposit.amount + term_earnings),
+                MaturityInstruction::RenewPrincipal => (Some(deposit.product_id), deposit.amount),
+            };
+            let renewal_terms =
+                product_id.and_then(|id| renewal_terms(deposit, id, renewal_amount, catalog, lock_end_time));
+            let Some(terms) = renewal_terms else {
+                balance.locked_balance -= deposit.amount;
+                deposit.amount += term_earnings;
+                deposit.status = DepositStatus::Matured;
+                balance.available_balance += deposit.amount;
+                continue;
+            };
+
+            if instruction == MaturityInstruction::RenewPrincipal {
+                if term_earnings > 0 {
+                    balance.available_balance += term_earnings;
+                    payouts.push(TokenDeposit {
+                        id: *next_deposit_id,
+                        amount: term_earnings,
+                        status: DepositStatus::Matured,
+                        maturity_instruction: MaturityInstruction::PayOut,
+                        ..deposit.clone()
+                    });
+                    *next_deposit_id += 1;
+                }
+            } else {
+                deposit.amount += term_earnings;
+                balance.locked_balance += term_earnings;
+            }
+
+            renew(deposit, terms, indices, lock_end_time);
+        }
+        refresh.accrued += deposit.accrue(&reward_index(indices, deposit.product_id), pool, current_time);
+    }
+    balance.deposits.extend(payouts);
+    refresh
+}
+
+/// The user's balance with maturities processed and interest accrued, for
+/// updates.
+pub fn refreshed_balance_mut<'a>(
+    state: &'a mut State,
+    user: &Principal,
+    current_time: u64,
+) -> Option<&'a mut UserBalance> {
+    let State {
+        user_balances,
+        next_deposit_id,
+        products,
+        reward_indices,
+        reward_pool,
+        loyalty,
+        transactions,
+        notifications,
+        ..
+    } = state;
+    let balance = user_balances.get_mut(user)?;
+    let refresh = refresh_deposits(balance, next_deposit_id, products, reward_indices, reward_pool, loyalty, current_time);
+    transactions::record_refresh(transactions, *user, &refresh);
+    notifications.record_refresh(*user, &refresh);
+    Some(balance)
+}
+
+/// A copy of the user's balance with maturities processed and interest
+/// accrued, for queries.
+pub fn refreshed_balance(state: &State, user: &Principal, current_time: u64) -> Option<UserBalance> {
+    let mut balance = state.user_balances.get(user)?.clone();
+    let mut next_deposit_id = state.next_deposit_id;
+    let mut reward_pool = state.reward_pool.clone();
+    refresh_deposits(
+        &mut balance,
+        &mut next_deposit_id,
+        &state.products,
+        &state.reward_indices,
+        &mut reward_pool,
+        &state.loyalty,
+        current_time,
+    );
+    Some(balance)
+}
+
+/// Refreshes deposits across all users.
+pub fn refresh_all(state: &mut State, current_time: u64) {
+    let State {
+        user_balances,
+        next_deposit_id,
+        products,
+        reward_indices,
+        reward_pool,
+        loyalty,
+        transactions,
+        notifications,
+        ..
+    } = state;
+    for (user, balance) in user_balances.iter_mut() {
+        let refresh =
+            refresh_deposits(balance, next_deposit_id, products, reward_indices, reward_pool, loyalty, current_time);
+        transactions::record_refresh(transactions, *user, &refresh);
+        notifications.record_refresh(*user, &refresh);
+    }
+}
+
+pub fn sweep(current_time: u64) {
+    STATE.with(|state| refresh_all(&mut state.borrow_mut(), current_time));
+}
+
+pub fn start_maturity_timer() {
+    ic_cdk_timers::set_timer_interval(MATURITY_SWEEP_INTERVAL, || sweep(time()));
+}
+
+/// Sweeps all balances now instead of waiting for the timer.
+#[ic_cdk::update]
+pub fn run_maturity_sweep() -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+    let current_time = time();
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Operator)?;
+        refresh_all(&mut state, current_time);
+        audit(&mut state, caller, AuditEvent::MaturitySweepRun);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{setup, DAY};
+    use crate::mock::set_time;
+    use crate::money::interest;
+    use crate::{burn_tokens, get_balance, mint_tokens, set_maturity_instruction, LockPeriod};
+
+    const THREE_MONTHS: u64 = 90 * DAY;
+
+    fn stored_balance() -> UserBalance {
+        STATE.with(|state| state.borrow().user_balances.values().next().cloned().unwrap())
+    }
+
+    #[test]
+    fn test_sweep_moves_matured_deposits() {
+        setup();
+        let three_month = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        let six_month = mint_tokens(20000, LockPeriod::SixMonths.into(), None).unwrap().deposited;
+
+        sweep(LockPeriod::ThreeMonths.duration_nanos() - 1);
+        assert_eq!(stored_balance().available_balance, 0);
+
+        sweep(LockPeriod::ThreeMonths.duration_nanos());
+        let balance = stored_balance();
+        let matured = three_month + interest(three_month, 500, THREE_MONTHS);
+        assert_eq!(balance.locked_balance, six_month);
+        assert_eq!(balance.available_balance, matured);
+        assert_eq!(balance.total_balance, matured + six_month);
+        assert_eq!(balance.deposits[0].status, DepositStatus::Matured);
+        assert_eq!(balance.deposits[1].status, DepositStatus::Locked);
+        // The six month deposit has accrued but not been paid its interest
+        assert_eq!(balance.deposits[1].accrued_interest, interest(six_month, 700, THREE_MONTHS));
+
+        // Sweeping again moves nothing twice
+        sweep(LockPeriod::ThreeMonths.duration_nanos());
+        assert_eq!(stored_balance().available_balance, matured);
+    }
+
+    #[test]
+    fn test_balance_matured_on_read() {
+        setup();
+        let token_amount = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        set_time(LockPeriod::ThreeMonths.duration_nanos());
+
+        let term_interest = interest(token_amount, 500, THREE_MONTHS);
+        let balance = get_balance().unwrap();
+        assert_eq!(balance.locked_balance, 0);
+        assert_eq!(balance.available_balance, token_amount + term_interest);
+        assert_eq!(balance.rewards_earned, term_interest);
+        assert_eq!(balance.deposits[0].status, DepositStatus::Matured);
+    }
+
+    #[test]
+    fn test_withdraw_from_matured_and_locked() {
+        setup();
+        let three_month = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        let six_month = mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap().deposited;
+        set_time(LockPeriod::ThreeMonths.duration_nanos());
+
+        let matured = three_month + interest(three_month, 500, THREE_MONTHS);
+        burn_tokens(matured + six_month / 2, None).unwrap();
+        let balance = stored_balance();
+        assert_eq!(balance.available_balance, 0);
+        assert_eq!(balance.locked_balance, six_month / 2);
+        assert_eq!(balance.total_balance, six_month / 2);
+        assert_eq!(balance.deposits.len(), 1);
+    }
+
+    #[test]
+    fn test_renew_same_period() {
+        setup();
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), Some(MaturityInstruction::Renew)).unwrap().deposited;
+        let first_term = interest(principal, 500, THREE_MONTHS);
+        let second_term = interest(principal + first_term, 500, THREE_MONTHS);
+
+        // Two terms have passed by the time the balance is next read
+        set_time(2 * THREE_MONTHS + 1);
+        let balance = get_balance().unwrap();
+        let deposit = &balance.deposits[0];
+        assert_eq!(deposit.status, DepositStatus::Locked);
+        assert_eq!(deposit.deposit_time, 2 * THREE_MONTHS);
+        assert_eq!(deposit.amount, principal + first_term + second_term);
+        assert_eq!(balance.locked_balance, deposit.amount);
+        assert_eq!(balance.total_balance, deposit.amount);
+        assert_eq!(balance.available_balance, 0);
+        assert_eq!(balance.rewards_earned, first_term + second_term);
+    }
+
+    #[test]
+    fn test_renew_as_other_period() {
+        setup();
+        let instruction = MaturityInstruction::RenewAs(ProductRef::TwelveMonths);
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), Some(instruction)).unwrap().deposited;
+        set_time(THREE_MONTHS);
+
+        let balance = get_balance().unwrap();
+        let deposit = &balance.deposits[0];
+        assert_eq!(deposit.product_id, LockPeriod::TwelveMonths.product_id());
+        assert_eq!(deposit.interest_rate_bps, LockPeriod::TwelveMonths.interest_rate());
+        assert_eq!(deposit.early_withdrawal_penalty_bps, LockPeriod::TwelveMonths.early_withdrawal_penalty());
+        assert_eq!(deposit.deposit_time, THREE_MONTHS);
+        assert_eq!(deposit.amount, principal + interest(principal, 500, THREE_MONTHS));
+    }
+
+    #[test]
+    fn test_renew_principal_pays_interest_out() {
+        setup();
+        let principal =
+            mint_tokens(10000, LockPeriod::ThreeMonths.into(), Some(MaturityInstruction::RenewPrincipal)).unwrap().deposited;
+        let term_interest = interest(principal, 500, THREE_MONTHS);
+        set_time(THREE_MONTHS);
+        sweep(THREE_MONTHS);
+
+        let balance = stored_balance();
+        assert_eq!(balance.deposits.len(), 2);
+        assert_eq!(balance.deposits[0].amount, principal);
+        assert_eq!(balance.deposits[0].status, DepositStatus::Locked);
+        assert_eq!(balance.deposits[1].id, 1);
+        assert_eq!(balance.deposits[1].amount, term_interest);
+        assert_eq!(balance.deposits[1].status, DepositStatus::Matured);
+        assert_eq!(balance.locked_balance, principal);
+        assert_eq!(balance.available_balance, term_interest);
+        assert_eq!(balance.total_balance, principal + term_interest);
+
+        // The paid-out interest can be withdrawn without penalty
+        assert_eq!(burn_tokens(term_interest, None).unwrap().net_payout, term_interest);
+        assert_eq!(stored_balance().locked_balance, principal);
+    }
+
+    #[test]
+    fn test_set_maturity_instruction() {
+        setup();
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap();
+
+        set_maturity_instruction(1, MaturityInstruction::Renew).unwrap();
+        assert_eq!(stored_balance().deposits[1].maturity_instruction, MaturityInstruction::Renew);
+        assert_eq!(set_maturity_instruction(7, MaturityInstruction::Renew).unwrap_err().to_string(), "Deposit not found");
+
+        set_time(THREE_MONTHS);
+        assert_eq!(
+            set_maturity_instruction(0, MaturityInstruction::Renew).unwrap_err().to_string(),
+            "Deposit has already matured"
+        );
+    }
+}
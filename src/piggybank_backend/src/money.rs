@@ -0,0 +1,111 @@
+//! Fixed-point arithmetic for token amounts and rates.
+//!
+//! All balances are held in integer minor units (`TOKEN_DECIMALS` decimals)
+//! and all rates and penalties are expressed in basis points. Intermediate
+//! products are computed in `u128` so nothing overflows before rounding.
+//!
+//! Rounding rules:
+//! - interest credited to a user is rounded down, so the ledger never pays
+//!   out more than the exact amount earned;
+//! - penalties charged to a user are rounded up, i.e. the payout is the
+//!   exact amount rounded down and the penalty is whatever remains.
+
+/// Number of decimals in a token amount.
+pub const TOKEN_DECIMALS: u32 = 6;
+
+/// Minor units in one whole token (1 USD).
+pub const TOKEN_UNIT: u64 = 10u64.pow(TOKEN_DECIMALS);
+
+/// Denominator for rates and penalties: 10_000 bps = 100%.
+pub const BASIS_POINTS: u64 = 10_000;
+
+/// Token minor units credited per whole INR (1 INR = 0.012 USD).
+pub const INR_TO_USD_RATE: u64 = TOKEN_UNIT * 12 / 1_000;
+
+pub const NANOS_PER_YEAR: u64 = 365 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Converts a whole-rupee amount into token minor units.
+pub fn inr_to_tokens(inr_amount: u64) -> Option<u64> {
+    inr_amount.checked_mul(INR_TO_USD_RATE)
+}
+
+/// Penalty in minor units for withdrawing `amount` at `penalty_bps`,
+/// rounded up.
+pub fn penalty(amount: u64, penalty_bps: u32) -> u64 {
+    let numerator = amount as u128 * penalty_bps as u128;
+    numerator.div_ceil(BASIS_POINTS as u128) as u64
+}
+
+/// `share_bps` of `amount`, rounded down.
+pub fn share(amount: u64, share_bps: u32) -> u64 {
+    (amount as u128 * share_bps as u128 / BASIS_POINTS as u128) as u64
+}
+
+/// Simple interest on `principal` at `rate_bps` APY over `elapsed_nanos`,
+/// rounded down.
+#[cfg(test)]
+pub fn interest(principal: u64, rate_bps: u32, elapsed_nanos: u64) -> u64 {
+    let numerator = principal as u128 * rate_bps as u128 * elapsed_nanos as u128;
+    let denominator = BASIS_POINTS as u128 * NANOS_PER_YEAR as u128;
+    (numerator / denominator) as u64
+}
+
+/// Like `interest`, but adds `carry` (the remainder left over by a previous
+/// call) before rounding and returns the new remainder alongside the
+/// interest, so accruing a period in several steps pays exactly the same
+/// total as accruing it in one.
+pub fn interest_with_carry(principal: u64, rate_bps: u32, elapsed_nanos: u64, carry: u128) -> (u64, u128) {
+    let numerator = principal as u128 * rate_bps as u128 * elapsed_nanos as u128 + carry;
+    let denominator = BASIS_POINTS as u128 * NANOS_PER_YEAR as u128;
+    ((numerator / denominator) as u64, numerator % denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_penalty_rounds_up() {
+        assert_eq!(penalty(10_000, 200), 200);
+        assert_eq!(penalty(10_001, 200), 201);
+        assert_eq!(penalty(1, 1), 1);
+        assert_eq!(penalty(0, 1_000), 0);
+    }
+
+    #[test]
+    fn test_share_rounds_down() {
+        assert_eq!(share(10_000, 2_500), 2_500);
+        assert_eq!(share(3, 5_000), 1);
+        assert_eq!(share(u64::MAX, 10_000), u64::MAX);
+    }
+
+    #[test]
+    fn test_interest_rounds_down() {
+        assert_eq!(interest(1_000_000, 500, NANOS_PER_YEAR), 50_000);
+        assert_eq!(interest(1_000_000, 500, NANOS_PER_YEAR / 2), 25_000);
+        assert_eq!(interest(19, 500, NANOS_PER_YEAR), 0);
+        assert_eq!(interest(10u64.pow(18), 1_000, NANOS_PER_YEAR), 10u64.pow(17));
+    }
+
+    #[test]
+    fn test_interest_with_carry_splits_exactly() {
+        let (whole, _) = interest_with_carry(1_234_567, 700, NANOS_PER_YEAR, 0);
+        assert_eq!(whole, interest(1_234_567, 700, NANOS_PER_YEAR));
+
+        let mut total = 0;
+        let mut carry = 0;
+        for _ in 0..1_000 {
+            let (step, rest) = interest_with_carry(1_234_567, 700, NANOS_PER_YEAR / 1_000, carry);
+            total += step;
+            carry = rest;
+        }
+        assert_eq!(total, whole);
+    }
+
+    #[test]
+    fn test_inr_to_tokens() {
+        assert_eq!(inr_to_tokens(1), Some(12_000));
+        assert_eq!(inr_to_tokens(10_000), Some(120 * TOKEN_UNIT));
+        assert_eq!(inr_to_tokens(u64::MAX), None);
+    }
+}
@@ -0,0 +1,350 @@
+//! Notifications.
+//!
+//! Each user has an inbox of notifications raised by events on their
+//! account: deposit terms completing, bonuses credited, penalties on early
+//! withdrawals, and progress on savings goals. Goals are also checked hourly,
+//! and their owner is warned once when a deadline is three days away, or
+//! again if the goal is completed and then reopened by a withdrawal.
+//!
+//! An inbox keeps at most `MAX_NOTIFICATIONS_PER_USER` notifications; past
+//! that, the oldest read one is dropped, or the oldest one if all are unread.
+
+use crate::error::Error;
+use crate::goals::{GoalStatus, SavingsGoal};
+use crate::maturity::Refresh;
+use crate::money::{TOKEN_DECIMALS, TOKEN_UNIT};
+use crate::state::State;
+use crate::{access, caller, time, STATE};
+use candid::{CandidType, Deserialize, Principal};
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+pub const MAX_NOTIFICATIONS_PER_USER: usize = 100;
+const DEADLINE_WARNING_NANOS: u64 = 3 * 24 * 60 * 60 * 1_000_000_000;
+const DEADLINE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Goal progress, in percent of the target, that raises a notification.
+const MILESTONES: [u64; 3] = [25, 50, 75];
+
+#[derive(CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NotificationType {
+    DepositMatured,
+    RewardsCredited,
+    PenaltyApplied,
+    MilestoneReached,
+    GoalCompleted,
+    DeadlineApproaching,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct Notification {
+    pub id: u64,
+    pub user_id: Principal,
+    pub notification_type: NotificationType,
+    pub message: String,
+    pub goal_id: Option<u64>,
+    pub created_at: u64,
+    pub read: bool,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct Notifications {
+    /// Each user's notifications, oldest first.
+    inboxes: HashMap<Principal, Vec<Notification>>,
+    next_id: u64,
+    /// Active goals whose owner has been warned of the deadline. Goals leave
+    /// it when they are completed or their deadline passes.
+    deadlines_notified: BTreeSet<u64>,
+}
+
+/// A token amount in whole tokens, e.g. "12.500000 tokens".
+fn tokens(amount: u64) -> String {
+    format!(
+        "{}.{:0width$} tokens",
+        amount / TOKEN_UNIT,
+        amount % TOKEN_UNIT,
+        width = TOKEN_DECIMALS as usize
+    )
+}
+
+impl Notifications {
+    pub fn push(
+        &mut self,
+        user: Principal,
+        notification_type: NotificationType,
+        message: String,
+        goal_id: Option<u64>,
+        created_at: u64,
+    ) {
+        let inbox = self.inboxes.entry(user).or_default();
+        inbox.push(Notification {
+            id: self.next_id,
+            user_id: user,
+            notification_type,
+            message,
+            goal_id,
+            created_at,
+            read: false,
+        });
+        self.next_id += 1;
+        if inbox.len() > MAX_NOTIFICATIONS_PER_USER {
+            let oldest = inbox.iter().position(|n| n.read).unwrap_or(0);
+            inbox.remove(oldest);
+        }
+    }
+
+    /// Notifies `user` of the terms completed, and loyalty bonuses paid, by a
+    /// refresh of their deposits.
+    pub fn record_refresh(&mut self, user: Principal, refresh: &Refresh) {
+        for term in &refresh.completed {
+            let message = format!(
+                "Deposit {} completed its term and earned {} in interest",
+                term.deposit_id,
+                tokens(term.interest)
+            );
+            self.push(user, NotificationType::DepositMatured, message, None, term.ended_at);
+            self.rewards_credited(user, "Loyalty bonus", term.loyalty_bonus, term.ended_at);
+        }
+    }
+
+    /// Notifies `user` of a bonus of `amount` paid into their balance.
+    pub fn rewards_credited(&mut self, user: Principal, bonus: &str, amount: u64, created_at: u64) {
+        if amount > 0 {
+            let message = format!("{} of {} credited", bonus, tokens(amount));
+            self.push(user, NotificationType::RewardsCredited, message, None, created_at);
+        }
+    }
+
+    pub fn penalty_applied(&mut self, user: Principal, penalty: u64, created_at: u64) {
+        if penalty > 0 {
+            let message = format!("An early withdrawal was charged a penalty of {}", tokens(penalty));
+            self.push(user, NotificationType::PenaltyApplied, message, None, created_at);
+        }
+    }
+
+    /// Notifies the owner of `goal` of its completion, or of the highest
+    /// milestone passed since it stood at `previous_amount`.
+    pub fn goal_progress(&mut self, goal: &SavingsGoal, previous_amount: u64, created_at: u64) {
+        if goal.status == GoalStatus::Completed {
+            self.deadlines_notified.remove(&goal.id);
+            let message = format!("Goal \"{}\" is complete", goal.name);
+            self.push(goal.user_id, NotificationType::GoalCompleted, message, Some(goal.id), created_at);
+            return;
+        }
+        let percent = |amount: u64| (amount as u128 * 100 / goal.target_amount as u128) as u64;
+        let (before, after) = (percent(previous_amount), percent(goal.current_amount));
+        if let Some(milestone) = MILESTONES.iter().rev().find(|&&m| before < m && m <= after) {
+            let message = format!("Goal \"{}\" is {}% of the way there", goal.name, milestone);
+            self.push(goal.user_id, NotificationType::MilestoneReached, message, Some(goal.id), created_at);
+        }
+    }
+}
+
+/// Warns the owners of active goals whose deadline is within three days,
+/// once per goal, and forgets goals whose deadline has passed.
+pub fn check_deadlines(state: &mut State, current_time: u64) {
+    let notifications = &mut state.notifications;
+    notifications.deadlines_notified.retain(|goal_id| {
+        let deadline = state.savings_goals.get(goal_id).and_then(|goal| goal.deadline);
+        deadline.is_some_and(|deadline| deadline > current_time)
+    });
+    for goal in state.savings_goals.values() {
+        let Some(deadline) = goal.deadline else {
+            continue;
+        };
+        if goal.status != GoalStatus::Active
+            || deadline <= current_time
+            || deadline - current_time > DEADLINE_WARNING_NANOS
+            || !notifications.deadlines_notified.insert(goal.id)
+        {
+            continue;
+        }
+        let message = format!("Goal \"{}\" is due in less than three days", goal.name);
+        notifications.push(goal.user_id, NotificationType::DeadlineApproaching, message, Some(goal.id), current_time);
+    }
+}
+
+pub fn start_deadline_timer() {
+    ic_cdk_timers::set_timer_interval(DEADLINE_CHECK_INTERVAL, || {
+        STATE.with(|state| check_deadlines(&mut state.borrow_mut(), time()))
+    });
+}
+
+/// The caller's notifications, oldest first, optionally leaving out those
+/// already read.
+#[ic_cdk::query]
+pub fn get_notifications(include_read: bool) -> Vec<Notification> {
+    let caller = caller();
+    STATE.with(|state| {
+        state
+            .borrow()
+            .notifications
+            .inboxes
+            .get(&caller)
+            .map(|inbox| inbox.iter().filter(|n| include_read || !n.read).cloned().collect())
+            .unwrap_or_default()
+    })
+}
+
+#[ic_cdk::update]
+pub fn mark_notification_as_read(id: u64) -> Result<(), Error> {
+    let caller = access::authenticated_caller()?;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let notification = state
+            .notifications
+            .inboxes
+            .get_mut(&caller)
+            .and_then(|inbox| inbox.iter_mut().find(|n| n.id == id))
+            .ok_or("Notification not found")?;
+        notification.read = true;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, other_user, user, DAY};
+    use crate::goals::{create_savings_goal, deposit, CreateGoalArgs, DepositArgs};
+    use crate::mock::{set_caller, set_time};
+    use crate::money::inr_to_tokens;
+    use crate::users::register_user;
+    use crate::{burn_tokens, get_balance, mint_tokens, LockPeriod};
+
+    fn setup() {
+        fixtures::setup();
+        set_time(DAY);
+        register_user("saver".to_string(), "saver@example.com".to_string()).unwrap();
+    }
+
+    fn create_goal(target_inr: u64, deadline: Option<u64>) -> u64 {
+        create_savings_goal(CreateGoalArgs {
+            name: "Car".to_string(),
+            target_amount: inr_to_tokens(target_inr).unwrap(),
+            category: "Savings".to_string(),
+            deadline,
+            product: None,
+        })
+        .unwrap()
+        .id
+    }
+
+    fn types(notifications: &[Notification]) -> Vec<NotificationType> {
+        notifications.iter().map(|n| n.notification_type).collect()
+    }
+
+    #[test]
+    fn test_goal_milestones_and_read_state() {
+        setup();
+        let goal_id = create_goal(10000, None);
+        deposit(DepositArgs { goal_id, amount: 1000 }).unwrap();
+        assert!(get_notifications(true).is_empty());
+
+        // Passing several milestones at once notifies the highest
+        deposit(DepositArgs { goal_id, amount: 6500 }).unwrap();
+        let notifications = get_notifications(true);
+        assert_eq!(types(&notifications), vec![NotificationType::MilestoneReached]);
+        assert_eq!(notifications[0].message, "Goal \"Car\" is 75% of the way there");
+        assert_eq!(notifications[0].goal_id, Some(goal_id));
+
+        deposit(DepositArgs { goal_id, amount: 2500 }).unwrap();
+        let notifications = get_notifications(true);
+        assert_eq!(types(&notifications), vec![NotificationType::MilestoneReached, NotificationType::GoalCompleted]);
+
+        mark_notification_as_read(notifications[0].id).unwrap();
+        assert_eq!(get_notifications(false), vec![notifications[1].clone()]);
+        assert!(get_notifications(true)[0].read);
+
+        // Notifications belong to their owner
+        set_caller(other_user());
+        assert!(get_notifications(true).is_empty());
+        assert_eq!(
+            mark_notification_as_read(notifications[1].id).unwrap_err().to_string(),
+            "Notification not found"
+        );
+    }
+
+    #[test]
+    fn test_deadline_warning_is_sent_once() {
+        setup();
+        create_goal(1000, Some(3 * DAY));
+        create_goal(1000, Some(10 * DAY));
+        let completed = create_goal(500, Some(3 * DAY));
+        deposit(DepositArgs { goal_id: completed, amount: 500 }).unwrap();
+
+        STATE.with(|state| check_deadlines(&mut state.borrow_mut(), DAY));
+        STATE.with(|state| check_deadlines(&mut state.borrow_mut(), 2 * DAY));
+        let deadlines = get_notifications(true)
+            .into_iter()
+            .filter(|n| n.notification_type == NotificationType::DeadlineApproaching)
+            .collect::<Vec<_>>();
+        assert_eq!(deadlines.len(), 1);
+        assert_eq!(deadlines[0].goal_id, Some(0));
+
+        STATE.with(|state| check_deadlines(&mut state.borrow_mut(), 8 * DAY));
+        assert_eq!(get_notifications(true).last().unwrap().goal_id, Some(1));
+    }
+
+    #[test]
+    fn test_reopened_goal_is_warned_again() {
+        setup();
+        let goal_id = create_goal(1000, Some(3 * DAY));
+        let deadline_warnings = || {
+            get_notifications(true)
+                .into_iter()
+                .filter(|n| n.notification_type == NotificationType::DeadlineApproaching)
+                .count()
+        };
+        STATE.with(|state| check_deadlines(&mut state.borrow_mut(), DAY));
+        assert_eq!(deadline_warnings(), 1);
+
+        deposit(DepositArgs { goal_id, amount: 1000 }).unwrap();
+        burn_tokens(1, None).unwrap();
+        STATE.with(|state| check_deadlines(&mut state.borrow_mut(), 2 * DAY));
+        assert_eq!(deadline_warnings(), 2);
+        STATE.with(|state| check_deadlines(&mut state.borrow_mut(), 2 * DAY));
+        assert_eq!(deadline_warnings(), 2);
+
+        // Once the deadline passes the goal is forgotten
+        STATE.with(|state| check_deadlines(&mut state.borrow_mut(), 3 * DAY));
+        assert!(STATE.with(|state| state.borrow().notifications.deadlines_notified.is_empty()));
+    }
+
+    #[test]
+    fn test_account_events() {
+        setup();
+        let term = LockPeriod::ThreeMonths.duration_nanos();
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        let quote = burn_tokens(1_000_000, None).unwrap();
+        set_time(DAY + term);
+        get_balance().unwrap();
+        // Queries do not raise notifications; the next update does
+        assert_eq!(types(&get_notifications(true)), vec![NotificationType::PenaltyApplied]);
+        burn_tokens(1, None).unwrap();
+
+        let notifications = get_notifications(true);
+        assert_eq!(types(&notifications), vec![NotificationType::PenaltyApplied, NotificationType::DepositMatured]);
+        assert!(notifications[0].message.ends_with(&tokens(quote.total_penalty)));
+        assert_eq!(notifications[1].created_at, DAY + term);
+    }
+
+    #[test]
+    fn test_inbox_retention() {
+        let mut notifications = Notifications::default();
+        let user = user();
+        for i in 0..MAX_NOTIFICATIONS_PER_USER as u64 {
+            notifications.push(user, NotificationType::RewardsCredited, String::new(), None, i);
+        }
+        notifications.inboxes.get_mut(&user).unwrap()[5].read = true;
+
+        // The oldest read notification goes first, then the oldest
+        notifications.push(user, NotificationType::RewardsCredited, String::new(), None, 100);
+        notifications.push(user, NotificationType::RewardsCredited, String::new(), None, 101);
+        let inbox = &notifications.inboxes[&user];
+        assert_eq!(inbox.len(), MAX_NOTIFICATIONS_PER_USER);
+        assert_eq!(inbox[0].id, 1);
+        assert!(inbox.iter().all(|n| n.id != 5));
+        assert_eq!(inbox.last().unwrap().id, 101);
+    }
+}
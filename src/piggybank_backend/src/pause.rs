@@ -0,0 +1,257 @@
+//! Emergency pause switches.
+//!
+//! Admins can pause minting, withdrawals and reward accrual independently,
+//! giving a reason that is shown to users. Endpoints of a paused operation
+//! fail with `Error::Paused`.
+//!
+//! Pausing rewards freezes every reward index, so no interest accrues for
+//! the paused time. Deposits that reach their lock end while rewards are
+//! paused still mature, with the interest accrued up to the pause but no
+//! loyalty bonus.
+
+use crate::access::{audit, authenticated_caller, require_role, AuditEvent, Role};
+use crate::error::Error;
+use crate::state::State;
+use crate::{maturity, rewards, time, STATE};
+use candid::{CandidType, Deserialize, Principal};
+
+#[derive(CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Operation {
+    /// `mint_tokens` and `mint_tokens_until`.
+    Minting,
+    /// `burn_tokens` and `withdraw_deposit`.
+    Withdrawals,
+    /// `apply_rewards` and interest accrual on every deposit.
+    Rewards,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct Pause {
+    pub reason: String,
+    pub paused_at: u64,
+    pub paused_by: Principal,
+}
+
+/// The pause on each operation, if any.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct PauseState {
+    pub minting: Option<Pause>,
+    pub withdrawals: Option<Pause>,
+    pub rewards: Option<Pause>,
+}
+
+impl PauseState {
+    fn slot(&mut self, operation: Operation) -> &mut Option<Pause> {
+        match operation {
+            Operation::Minting => &mut self.minting,
+            Operation::Withdrawals => &mut self.withdrawals,
+            Operation::Rewards => &mut self.rewards,
+        }
+    }
+
+    pub fn is_paused(&self, operation: Operation) -> bool {
+        self.get(operation).is_some()
+    }
+
+    fn get(&self, operation: Operation) -> Option<&Pause> {
+        match operation {
+            Operation::Minting => self.minting.as_ref(),
+            Operation::Withdrawals => self.withdrawals.as_ref(),
+            Operation::Rewards => self.rewards.as_ref(),
+        }
+    }
+
+    /// Fails with `Error::Paused` if `operation` is paused.
+    pub fn check(&self, operation: Operation) -> Result<(), Error> {
+        match self.get(operation) {
+            Some(pause) => Err(Error::Paused {
+                operation,
+                reason: pause.reason.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Fails with `Error::Paused` if `operation` is paused.
+pub fn check(operation: Operation) -> Result<(), Error> {
+    STATE.with(|state| state.borrow().pause.check(operation))
+}
+
+#[ic_cdk::update]
+pub fn pause_operation(operation: Operation, reason: String) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+    let current_time = time();
+    if reason.trim().is_empty() {
+        return Err("Pause reason must not be empty".into());
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        let slot = state.pause.slot(operation);
+        if slot.is_some() {
+            return Err("Operation is already paused".into());
+        }
+        *slot = Some(Pause {
+            reason: reason.clone(),
+            paused_at: current_time,
+            paused_by: caller,
+        });
+        if operation == Operation::Rewards {
+            // Terms completed before the pause still earn their bonus
+            maturity::refresh_all(&mut state, current_time);
+            rewards::accrue_all(&mut state, current_time);
+        }
+        audit(&mut state, caller, AuditEvent::Paused { operation, reason });
+        Ok(())
+    })
+}
+
+#[ic_cdk::update]
+pub fn resume_operation(operation: Operation) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+    let current_time = time();
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        if !state.pause.is_paused(operation) {
+            return Err("Operation is not paused".into());
+        }
+        if operation == Operation::Rewards {
+            resume_rewards(&mut state, current_time);
+        }
+        *state.pause.slot(operation) = None;
+        audit(&mut state, caller, AuditEvent::Resumed { operation });
+        Ok(())
+    })
+}
+
+/// Settles every deposit against the frozen indices, then restarts them.
+/// Indices only answer for times after they restart, so no deposit may be
+/// left accrued to before `current_time`.
+fn resume_rewards(state: &mut State, current_time: u64) {
+    maturity::refresh_all(state, current_time);
+    for index in state.reward_indices.values_mut() {
+        index.resume(current_time);
+    }
+}
+
+#[ic_cdk::query]
+pub fn get_pause_state() -> PauseState {
+    STATE.with(|state| state.borrow().pause.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{admin, as_admin, setup, DAY};
+    use crate::mock::{set_caller, set_time};
+    use crate::money::interest;
+    use crate::withdrawal::quote_withdrawal;
+    use crate::{apply_rewards, burn_tokens, get_balance, mint_tokens, mint_tokens_until, withdraw_deposit, LockPeriod};
+
+    fn set_paused(operation: Operation, paused: bool) {
+        as_admin(|| {
+            if paused {
+                pause_operation(operation, "Incident 42".to_string()).unwrap();
+            } else {
+                resume_operation(operation).unwrap();
+            }
+        });
+    }
+
+    fn paused(operation: Operation) -> Error {
+        Error::Paused {
+            operation,
+            reason: "Incident 42".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_paused_endpoints_fail() {
+        setup();
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        let deposit_id = get_balance().unwrap().deposits[0].id;
+
+        set_paused(Operation::Minting, true);
+        assert_eq!(mint_tokens(10000, LockPeriod::ThreeMonths.into(), None), Err(paused(Operation::Minting)));
+        assert_eq!(mint_tokens_until(10000, 100 * DAY, None), Err(paused(Operation::Minting)));
+        assert!(burn_tokens(1, None).is_ok());
+
+        set_paused(Operation::Withdrawals, true);
+        assert_eq!(burn_tokens(1, None), Err(paused(Operation::Withdrawals)));
+        assert_eq!(withdraw_deposit(deposit_id, 1), Err(paused(Operation::Withdrawals)));
+        assert_eq!(quote_withdrawal(1, None), Err(paused(Operation::Withdrawals)));
+        assert!(apply_rewards().is_ok());
+
+        set_paused(Operation::Rewards, true);
+        assert_eq!(apply_rewards(), Err(paused(Operation::Rewards)));
+
+        let state = get_pause_state();
+        assert_eq!(state.minting.unwrap().paused_by, admin());
+        assert!(state.withdrawals.is_some() && state.rewards.is_some());
+
+        set_paused(Operation::Minting, false);
+        assert!(mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).is_ok());
+        assert_eq!(get_pause_state().minting, None);
+    }
+
+    #[test]
+    fn test_pause_errors() {
+        setup();
+        assert!(pause_operation(Operation::Minting, "Incident".to_string()).is_err());
+
+        set_caller(admin());
+        assert!(pause_operation(Operation::Minting, " ".to_string()).is_err());
+        assert_eq!(resume_operation(Operation::Minting).unwrap_err().to_string(), "Operation is not paused");
+        pause_operation(Operation::Minting, "Incident".to_string()).unwrap();
+        assert_eq!(
+            pause_operation(Operation::Minting, "Again".to_string()).unwrap_err().to_string(),
+            "Operation is already paused"
+        );
+    }
+
+    #[test]
+    fn test_no_interest_accrues_while_rewards_paused() {
+        setup();
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        let term = LockPeriod::ThreeMonths.duration_nanos();
+
+        set_time(10 * DAY);
+        set_paused(Operation::Rewards, true);
+        set_time(30 * DAY);
+        assert_eq!(get_balance().unwrap().deposits[0].accrued_interest, interest(principal, 500, 10 * DAY));
+
+        // Accrual picks up again from the resume time
+        set_paused(Operation::Rewards, false);
+        set_time(40 * DAY);
+        apply_rewards().unwrap();
+        assert_eq!(get_balance().unwrap().deposits[0].accrued_interest, interest(principal, 500, 20 * DAY));
+
+        // The term still ends on schedule, 20 days of interest short
+        set_time(term);
+        let deposit = get_balance().unwrap().deposits[0].clone();
+        assert_eq!(deposit.amount, principal + interest(principal, 500, term - 20 * DAY));
+    }
+
+    #[test]
+    fn test_deposit_maturing_while_rewards_paused() {
+        setup();
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        let term = LockPeriod::ThreeMonths.duration_nanos();
+
+        set_time(term - 10 * DAY);
+        set_paused(Operation::Rewards, true);
+        // Deposits opened while paused earn nothing until rewards resume
+        mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap();
+        set_time(term + 10 * DAY);
+        set_paused(Operation::Rewards, false);
+
+        set_time(term + 20 * DAY);
+        let balance = get_balance().unwrap();
+        assert_eq!(balance.deposits[0].amount, principal + interest(principal, 500, term - 10 * DAY));
+        assert_eq!(balance.deposits[1].accrued_interest, interest(principal, 700, 10 * DAY));
+    }
+}
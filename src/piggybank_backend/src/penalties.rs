@@ -0,0 +1,151 @@
+//! The penalty account.
+//!
+//! Early withdrawal penalties are booked here, with a running total for each
+//! product. Admins can pass a share of every penalty on to the reward pool;
+//! the rest stays in the account.
+
+use crate::access::{audit, authenticated_caller, require_role, AuditEvent, Role};
+use crate::error::Error;
+use crate::money::{share, BASIS_POINTS};
+use crate::products::ProductId;
+use crate::state::State;
+use crate::withdrawal::WithdrawalQuote;
+use crate::{caller, STATE};
+use candid::{CandidType, Deserialize};
+use std::collections::BTreeMap;
+
+/// Penalties collected, in token minor units.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct PenaltyAccount {
+    /// Penalties held after the reward pool's share.
+    pub balance: u64,
+    pub total: u64,
+    /// Penalties charged on each product's deposits.
+    pub by_product: BTreeMap<ProductId, u64>,
+    /// Passed on to the reward pool.
+    pub to_reward_pool: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Penalties {
+    reward_pool_share_bps: u32,
+    pub account: PenaltyAccount,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct PenaltyAccountStatus {
+    pub account: PenaltyAccount,
+    pub reward_pool_share_bps: u32,
+}
+
+/// Books the penalties of an executed withdrawal and passes the reward pool
+/// its share, rounded down.
+pub fn collect(state: &mut State, quote: &WithdrawalQuote) {
+    let penalties = &mut state.penalties;
+    let to_reward_pool = share(quote.total_penalty, penalties.reward_pool_share_bps);
+    let account = &mut penalties.account;
+    for leg in quote.legs.iter().filter(|leg| leg.penalty > 0) {
+        *account.by_product.entry(leg.product_id).or_default() += leg.penalty;
+    }
+    account.total += quote.total_penalty;
+    account.balance += quote.total_penalty - to_reward_pool;
+    account.to_reward_pool += to_reward_pool;
+    state.reward_pool.credit_penalties(to_reward_pool);
+}
+
+/// Sets the share of every penalty passed on to the reward pool.
+#[ic_cdk::update]
+pub fn set_penalty_reward_pool_share(share_bps: u32) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+    if share_bps as u64 > BASIS_POINTS {
+        return Err("Share must not exceed 10000 basis points".into());
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        state.penalties.reward_pool_share_bps = share_bps;
+        audit(&mut state, caller, AuditEvent::PenaltyRewardPoolShareSet { share_bps });
+        Ok(())
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_penalty_account() -> Result<PenaltyAccountStatus, Error> {
+    let caller = caller();
+    STATE.with(|state| {
+        let state = state.borrow();
+        require_role(&state, &caller, Role::Auditor)?;
+        Ok(PenaltyAccountStatus {
+            account: state.penalties.account.clone(),
+            reward_pool_share_bps: state.penalties.reward_pool_share_bps,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{admin, as_admin, setup};
+    use crate::mock::set_caller;
+    use crate::money::penalty;
+    use crate::{burn_tokens, get_balance, mint_tokens, withdraw_deposit, LockPeriod};
+
+    fn pool_balance() -> u64 {
+        STATE.with(|state| state.borrow().reward_pool.balance)
+    }
+
+    #[test]
+    fn test_penalties_booked_by_product() {
+        setup();
+        let three_month = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        let twelve_month = mint_tokens(10000, LockPeriod::TwelveMonths.into(), None).unwrap().deposited;
+        let pool_before = pool_balance();
+
+        // Least-penalty order draws the 3-month deposit dry first
+        let quote = burn_tokens(three_month + 1_000, None).unwrap();
+        assert_eq!(quote.legs[1].product_id, LockPeriod::TwelveMonths.product_id());
+        let twelve_month_id = get_balance().unwrap().deposits[0].id;
+        withdraw_deposit(twelve_month_id, twelve_month - 1_000).unwrap();
+
+        set_caller(admin());
+        let account = get_penalty_account().unwrap().account;
+        assert_eq!(account.by_product[&LockPeriod::ThreeMonths.product_id()], penalty(three_month, 200));
+        assert_eq!(account.by_product[&LockPeriod::TwelveMonths.product_id()], penalty(1_000, 1000) + penalty(twelve_month - 1_000, 1000));
+        assert_eq!(account.total, account.by_product.values().sum::<u64>());
+        assert_eq!(account.balance, account.total);
+        // Without a share nothing reaches the reward pool
+        assert_eq!(pool_balance(), pool_before);
+    }
+
+    #[test]
+    fn test_reward_pool_share() {
+        setup();
+        as_admin(|| set_penalty_reward_pool_share(5_000).unwrap());
+        let deposited = mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap().deposited;
+        let pool_before = pool_balance();
+
+        let quote = burn_tokens(deposited, None).unwrap();
+        assert_eq!(quote.total_penalty, penalty(deposited, 500));
+
+        set_caller(admin());
+        let status = get_penalty_account().unwrap();
+        assert_eq!(status.reward_pool_share_bps, 5_000);
+        assert_eq!(status.account.to_reward_pool, quote.total_penalty / 2);
+        assert_eq!(status.account.balance, quote.total_penalty - quote.total_penalty / 2);
+        assert_eq!(pool_balance(), pool_before + quote.total_penalty / 2);
+        assert_eq!(STATE.with(|state| state.borrow().reward_pool.penalties), quote.total_penalty / 2);
+    }
+
+    #[test]
+    fn test_penalty_account_access() {
+        setup();
+        assert_eq!(get_penalty_account().unwrap_err().to_string(), "Caller does not have the Auditor role");
+        assert_eq!(set_penalty_reward_pool_share(1).unwrap_err().to_string(), "Caller does not have the Admin role");
+
+        set_caller(admin());
+        assert!(set_penalty_reward_pool_share(10_001).is_err());
+        set_penalty_reward_pool_share(10_000).unwrap();
+        assert_eq!(get_penalty_account().unwrap().reward_pool_share_bps, 10_000);
+    }
+}
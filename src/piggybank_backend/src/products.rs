@@ -0,0 +1,361 @@
+//! The savings product catalog.
+//!
+//! A product defines the terms deposits are opened with. Deposits copy their
+//! product's terms when they are opened or renewed, so editing a product never
+//! changes deposits already running under it. The original lock periods are
+//! built-in products with fixed IDs.
+//!
+//! A product's own rates apply until a rate sheet covering it takes effect;
+//! see `rates`.
+
+use crate::money::BASIS_POINTS;
+use crate::rates::{ProductRates, RateSheet};
+use crate::access::{audit, authenticated_caller, require_role, AuditEvent, Role};
+use crate::error::Error;
+use crate::{rewards, time, DepositTerms, LockPeriod, STATE};
+use candid::{CandidType, Deserialize};
+use std::collections::BTreeMap;
+
+pub type ProductId = u64;
+
+/// Lock periods that map to built-in products, in product ID order.
+const BUILT_IN: [LockPeriod; 3] = [LockPeriod::ThreeMonths, LockPeriod::SixMonths, LockPeriod::TwelveMonths];
+
+/// Deposit limits are in token minor units, rates are in basis points.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct Product {
+    pub id: ProductId,
+    pub name: String,
+    pub duration_nanos: u64,
+    pub interest_rate_bps: u32,
+    pub early_withdrawal_penalty_bps: u32,
+    pub min_deposit: u64,
+    pub max_deposit: Option<u64>,
+    /// Closed products take no new deposits or renewals.
+    pub open: bool,
+}
+
+/// Everything about a product an admin can set.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ProductArgs {
+    pub name: String,
+    pub duration_nanos: u64,
+    pub interest_rate_bps: u32,
+    pub early_withdrawal_penalty_bps: u32,
+    pub min_deposit: u64,
+    pub max_deposit: Option<u64>,
+    pub open: bool,
+}
+
+/// A product named either by ID or by one of the original lock periods, so
+/// clients that still send a `LockPeriod` keep working.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub enum ProductRef {
+    ThreeMonths,
+    SixMonths,
+    TwelveMonths,
+    Id(ProductId),
+}
+
+impl ProductRef {
+    pub fn id(&self) -> ProductId {
+        match self {
+            ProductRef::ThreeMonths => LockPeriod::ThreeMonths.product_id(),
+            ProductRef::SixMonths => LockPeriod::SixMonths.product_id(),
+            ProductRef::TwelveMonths => LockPeriod::TwelveMonths.product_id(),
+            ProductRef::Id(id) => *id,
+        }
+    }
+}
+
+impl From<LockPeriod> for ProductRef {
+    fn from(lock_period: LockPeriod) -> Self {
+        match lock_period {
+            LockPeriod::ThreeMonths => ProductRef::ThreeMonths,
+            LockPeriod::SixMonths => ProductRef::SixMonths,
+            LockPeriod::TwelveMonths => ProductRef::TwelveMonths,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Catalog {
+    products: BTreeMap<ProductId, Product>,
+    next_product_id: ProductId,
+    /// Published rate sheets in order of `effective_from`.
+    pub(crate) rate_sheets: Vec<RateSheet>,
+}
+
+impl Default for Catalog {
+    /// A catalog holding just the built-in products.
+    fn default() -> Self {
+        let products: BTreeMap<ProductId, Product> = BUILT_IN
+            .iter()
+            .map(|lock_period| {
+                let product = Product {
+                    id: lock_period.product_id(),
+                    name: lock_period.name().to_string(),
+                    duration_nanos: lock_period.duration_nanos(),
+                    interest_rate_bps: lock_period.interest_rate(),
+                    early_withdrawal_penalty_bps: lock_period.early_withdrawal_penalty(),
+                    min_deposit: 0,
+                    max_deposit: None,
+                    open: true,
+                };
+                (product.id, product)
+            })
+            .collect();
+        let next_product_id = products.len() as ProductId;
+        Catalog {
+            products,
+            next_product_id,
+            rate_sheets: Vec::new(),
+        }
+    }
+}
+
+impl Catalog {
+    pub fn get(&self, id: ProductId) -> Option<&Product> {
+        self.products.get(&id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = ProductId> + '_ {
+        self.products.keys().copied()
+    }
+
+    /// Rates in force for `product` at `time`.
+    pub fn rates_at(&self, product: &Product, time: u64) -> ProductRates {
+        self.rate_sheets
+            .iter()
+            .rev()
+            .filter(|sheet| sheet.effective_from <= time)
+            .find_map(|sheet| sheet.rates.iter().find(|r| r.product_id == product.id))
+            .cloned()
+            .unwrap_or(ProductRates {
+                product_id: product.id,
+                interest_rate_bps: product.interest_rate_bps,
+                early_withdrawal_penalty_bps: product.early_withdrawal_penalty_bps,
+            })
+    }
+
+    /// Terms a new deposit or renewal of `amount` would get in product `id`
+    /// at `time`, if the product is open and takes that amount.
+    pub fn terms_for_deposit(&self, id: ProductId, amount: u64, time: u64) -> Result<DepositTerms, String> {
+        let product = self.product_for_deposit(id, amount)?;
+        let rates = self.rates_at(product, time);
+        Ok(DepositTerms {
+            product_id: product.id,
+            duration_nanos: product.duration_nanos,
+            interest_rate_bps: rates.interest_rate_bps,
+            early_withdrawal_penalty_bps: rates.early_withdrawal_penalty_bps,
+        })
+    }
+
+    fn product_for_deposit(&self, id: ProductId, amount: u64) -> Result<&Product, String> {
+        let product = self.get(id).ok_or("Product not found")?;
+        if !product.open {
+            return Err("Product is closed".to_string());
+        }
+        if amount < product.min_deposit {
+            return Err("Amount is below the product minimum".to_string());
+        }
+        if product.max_deposit.is_some_and(|max| amount > max) {
+            return Err("Amount is above the product maximum".to_string());
+        }
+        Ok(product)
+    }
+}
+
+fn validate(args: &ProductArgs) -> Result<(), String> {
+    if args.name.trim().is_empty() {
+        return Err("Product name must not be empty".to_string());
+    }
+    if args.duration_nanos == 0 {
+        return Err("Product duration must be greater than 0".to_string());
+    }
+    if args.interest_rate_bps as u64 > BASIS_POINTS || args.early_withdrawal_penalty_bps as u64 > BASIS_POINTS {
+        return Err("Rates must not exceed 10000 basis points".to_string());
+    }
+    if args.max_deposit.is_some_and(|max| max < args.min_deposit) {
+        return Err("Maximum deposit is below the minimum".to_string());
+    }
+    Ok(())
+}
+
+fn product(id: ProductId, args: ProductArgs) -> Product {
+    Product {
+        id,
+        name: args.name,
+        duration_nanos: args.duration_nanos,
+        interest_rate_bps: args.interest_rate_bps,
+        early_withdrawal_penalty_bps: args.early_withdrawal_penalty_bps,
+        min_deposit: args.min_deposit,
+        max_deposit: args.max_deposit,
+        open: args.open,
+    }
+}
+
+/// Adds a product to the catalog and returns its ID.
+#[ic_cdk::update]
+pub fn add_product(args: ProductArgs) -> Result<ProductId, Error> {
+    let caller = authenticated_caller()?;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        validate(&args)?;
+        let catalog = &mut state.products;
+        let id = catalog.next_product_id;
+        catalog.next_product_id += 1;
+        catalog.products.insert(id, product(id, args));
+        // Starts the product's index, frozen if rewards are paused
+        rewards::accrue_all(&mut state, time());
+        audit(&mut state, caller, AuditEvent::ProductAdded { product_id: id });
+        Ok(id)
+    })
+}
+
+/// Replaces a product's terms. Deposits already opened keep the terms they
+/// were opened with.
+#[ic_cdk::update]
+pub fn update_product(id: ProductId, args: ProductArgs) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        validate(&args)?;
+        let existing = state.products.products.get_mut(&id).ok_or("Product not found")?;
+        *existing = product(id, args);
+        audit(&mut state, caller, AuditEvent::ProductUpdated { product_id: id });
+        Ok(())
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_products() -> Vec<Product> {
+    STATE.with(|state| state.borrow().products.products.values().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, admin, user, DAY};
+    use crate::mock::{set_caller, set_time};
+    use crate::{get_balance, mint_tokens, DepositStatus};
+
+    const ONE_MONTH: u64 = 30 * DAY;
+
+    fn setup() {
+        fixtures::setup();
+        set_caller(admin());
+    }
+
+    fn one_month() -> ProductArgs {
+        ProductArgs {
+            name: "1 month".to_string(),
+            duration_nanos: ONE_MONTH,
+            interest_rate_bps: 300,
+            early_withdrawal_penalty_bps: 100,
+            min_deposit: 12_000_000,
+            max_deposit: Some(120_000_000),
+            open: true,
+        }
+    }
+
+    #[test]
+    fn test_built_in_products() {
+        setup();
+        let products = get_products();
+        assert_eq!(products.len(), 3);
+        for (product, lock_period) in products.iter().zip(BUILT_IN) {
+            assert_eq!(product.id, ProductRef::from(lock_period.clone()).id());
+            assert_eq!(product.duration_nanos, lock_period.duration_nanos());
+            assert_eq!(product.interest_rate_bps, lock_period.interest_rate());
+        }
+    }
+
+    #[test]
+    fn test_add_product_and_mint() {
+        setup();
+        let id = add_product(one_month()).unwrap();
+        assert_eq!(id, 3);
+
+        assert_eq!(mint_tokens(999, ProductRef::Id(id), None).unwrap_err().to_string(), "Amount is below the product minimum");
+        assert_eq!(mint_tokens(10001, ProductRef::Id(id), None).unwrap_err().to_string(), "Amount is above the product maximum");
+        assert_eq!(mint_tokens(1000, ProductRef::Id(7), None).unwrap_err().to_string(), "Product not found");
+
+        mint_tokens(1000, ProductRef::Id(id), None).unwrap();
+        let deposit = &get_balance().unwrap().deposits[0];
+        assert_eq!(deposit.product_id, id);
+        assert_eq!(deposit.interest_rate_bps, 300);
+        assert_eq!(deposit.lock_end_time(), ONE_MONTH);
+    }
+
+    #[test]
+    fn test_closed_product_takes_no_deposits() {
+        setup();
+        let id = add_product(ProductArgs {
+            open: false,
+            ..one_month()
+        })
+        .unwrap();
+        assert_eq!(mint_tokens(1000, ProductRef::Id(id), None).unwrap_err().to_string(), "Product is closed");
+    }
+
+    #[test]
+    fn test_update_product_keeps_existing_terms() {
+        setup();
+        let id = add_product(one_month()).unwrap();
+        mint_tokens(1000, ProductRef::Id(id), Some(crate::MaturityInstruction::Renew)).unwrap();
+
+        update_product(id, ProductArgs {
+            interest_rate_bps: 900,
+            duration_nanos: 2 * ONE_MONTH,
+            ..one_month()
+        })
+        .unwrap();
+        let deposit = get_balance().unwrap().deposits[0].clone();
+        assert_eq!(deposit.interest_rate_bps, 300);
+        assert_eq!(deposit.lock_end_time(), ONE_MONTH);
+
+        // The renewal picks up the new terms
+        set_time(ONE_MONTH);
+        let deposit = get_balance().unwrap().deposits[0].clone();
+        assert_eq!(deposit.status, DepositStatus::Locked);
+        assert_eq!(deposit.interest_rate_bps, 900);
+        assert_eq!(deposit.lock_end_time(), 3 * ONE_MONTH);
+
+        // Closing the product pays the deposit out at its next maturity
+        update_product(id, ProductArgs {
+            open: false,
+            ..one_month()
+        })
+        .unwrap();
+        set_time(3 * ONE_MONTH);
+        assert_eq!(get_balance().unwrap().deposits[0].status, DepositStatus::Matured);
+    }
+
+    #[test]
+    fn test_product_admin_errors() {
+        setup();
+        assert_eq!(
+            add_product(ProductArgs {
+                name: " ".to_string(),
+                ..one_month()
+            })
+            .unwrap_err().to_string(),
+            "Product name must not be empty"
+        );
+        assert!(add_product(ProductArgs {
+            max_deposit: Some(1),
+            ..one_month()
+        })
+        .is_err());
+        assert_eq!(update_product(7, one_month()).unwrap_err().to_string(), "Product not found");
+
+        set_caller(user());
+        assert_eq!(add_product(one_month()).unwrap_err().to_string(), "Caller does not have the Admin role");
+        assert_eq!(get_products().len(), 3);
+    }
+}
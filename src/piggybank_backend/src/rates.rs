@@ -0,0 +1,179 @@
+//! Effective-dated rate sheets.
+//!
+//! Rates change by publishing a rate sheet that takes effect at a given time,
+//! now or later. The rates in force for a product at any time come from the
+//! latest sheet effective by then that covers the product, or from the
+//! product itself if none does. Deposits copy the rates in force when they
+//! are opened or renewed, so a new sheet never reprices a running term.
+
+use crate::access::{audit, authenticated_caller, require_role, AuditEvent, Role};
+use crate::error::Error;
+use crate::money::BASIS_POINTS;
+use crate::products::ProductId;
+use crate::{time, STATE};
+use candid::{CandidType, Deserialize};
+
+/// Rates for one product, in basis points.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct ProductRates {
+    pub product_id: ProductId,
+    pub interest_rate_bps: u32,
+    pub early_withdrawal_penalty_bps: u32,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct RateSheet {
+    /// Position in the rate history, starting at 0.
+    pub id: u64,
+    pub effective_from: u64,
+    pub published_at: u64,
+    pub rates: Vec<ProductRates>,
+}
+
+/// Publishes `rates` to take effect at `effective_from`. Sheets cannot be
+/// backdated and take effect in the order they are published.
+#[ic_cdk::update]
+pub fn publish_rate_sheet(effective_from: u64, rates: Vec<ProductRates>) -> Result<u64, Error> {
+    let caller = authenticated_caller()?;
+    let current_time = time();
+    if effective_from < current_time {
+        return Err("Rate sheet cannot take effect in the past".into());
+    }
+    if rates.is_empty() {
+        return Err("Rate sheet must not be empty".into());
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        let catalog = &mut state.products;
+        for (i, rate) in rates.iter().enumerate() {
+            if catalog.get(rate.product_id).is_none() {
+                return Err("Product not found".into());
+            }
+            if rates[..i].iter().any(|r| r.product_id == rate.product_id) {
+                return Err("Rate sheet lists a product more than once".into());
+            }
+            if rate.interest_rate_bps as u64 > BASIS_POINTS || rate.early_withdrawal_penalty_bps as u64 > BASIS_POINTS {
+                return Err("Rates must not exceed 10000 basis points".into());
+            }
+        }
+        if catalog.rate_sheets.last().is_some_and(|last| effective_from <= last.effective_from) {
+            return Err("Rate sheet must take effect after the latest published sheet".into());
+        }
+
+        let id = catalog.rate_sheets.len() as u64;
+        catalog.rate_sheets.push(RateSheet {
+            id,
+            effective_from,
+            published_at: current_time,
+            rates,
+        });
+        audit(&mut state, caller, AuditEvent::RateSheetPublished { rate_sheet_id: id });
+        Ok(id)
+    })
+}
+
+/// Every published rate sheet, oldest first, including ones not yet in
+/// effect.
+#[ic_cdk::query]
+pub fn get_rate_sheets() -> Vec<RateSheet> {
+    STATE.with(|state| state.borrow().products.rate_sheets.clone())
+}
+
+/// Rates in force for every product at `at`, or now.
+#[ic_cdk::query]
+pub fn get_rates_at(at: Option<u64>) -> Vec<ProductRates> {
+    let at = at.unwrap_or_else(time);
+    STATE.with(|state| {
+        let catalog = &state.borrow().products;
+        catalog
+            .ids()
+            .filter_map(|id| catalog.get(id))
+            .map(|product| catalog.rates_at(product, at))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, admin, user, DAY};
+    use crate::mock::{set_caller, set_time};
+    use crate::money::interest;
+    use crate::{get_balance, mint_tokens, LockPeriod, MaturityInstruction};
+
+    fn setup() {
+        fixtures::setup();
+        set_caller(admin());
+    }
+
+    fn three_month_rates(interest_rate_bps: u32) -> Vec<ProductRates> {
+        vec![ProductRates {
+            product_id: LockPeriod::ThreeMonths.product_id(),
+            interest_rate_bps,
+            early_withdrawal_penalty_bps: 300,
+        }]
+    }
+
+    #[test]
+    fn test_rates_take_effect_on_schedule() {
+        setup();
+        publish_rate_sheet(10 * DAY, three_month_rates(600)).unwrap();
+        publish_rate_sheet(20 * DAY, three_month_rates(650)).unwrap();
+
+        assert_eq!(get_rates_at(None)[0].interest_rate_bps, 500);
+        assert_eq!(get_rates_at(Some(10 * DAY))[0].interest_rate_bps, 600);
+        assert_eq!(get_rates_at(Some(25 * DAY))[0].interest_rate_bps, 650);
+        // Products a sheet leaves out keep their rates
+        assert_eq!(get_rates_at(Some(25 * DAY))[1].interest_rate_bps, 700);
+
+        set_time(10 * DAY);
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        let deposit = &get_balance().unwrap().deposits[0];
+        assert_eq!(deposit.interest_rate_bps, 600);
+        assert_eq!(deposit.early_withdrawal_penalty_bps, 300);
+        assert_eq!(get_rate_sheets().len(), 2);
+    }
+
+    #[test]
+    fn test_new_sheet_does_not_reprice_deposits() {
+        setup();
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), Some(MaturityInstruction::Renew)).unwrap().deposited;
+        let term = LockPeriod::ThreeMonths.duration_nanos();
+        publish_rate_sheet(DAY, three_month_rates(900)).unwrap();
+
+        // The running term keeps its rate after the sheet takes effect
+        set_time(term - 1);
+        let deposit = get_balance().unwrap().deposits[0].clone();
+        assert_eq!(deposit.interest_rate_bps, 500);
+        assert_eq!(deposit.accrued_interest, interest(principal, 500, term - 1));
+
+        // The renewal is priced at the rates in force at the renewal time
+        set_time(term);
+        let deposit = get_balance().unwrap().deposits[0].clone();
+        assert_eq!(deposit.interest_rate_bps, 900);
+        assert_eq!(deposit.amount, principal + interest(principal, 500, term));
+    }
+
+    #[test]
+    fn test_publish_rate_sheet_errors() {
+        setup();
+        set_time(DAY);
+        assert!(publish_rate_sheet(0, three_month_rates(600)).is_err());
+        assert_eq!(publish_rate_sheet(DAY, vec![]).unwrap_err().to_string(), "Rate sheet must not be empty");
+        let mut unknown = three_month_rates(600);
+        unknown[0].product_id = 7;
+        assert_eq!(publish_rate_sheet(DAY, unknown).unwrap_err().to_string(), "Product not found");
+        let mut duplicate = three_month_rates(600);
+        duplicate.extend(three_month_rates(700));
+        assert!(publish_rate_sheet(DAY, duplicate).is_err());
+
+        publish_rate_sheet(2 * DAY, three_month_rates(600)).unwrap();
+        assert!(publish_rate_sheet(2 * DAY, three_month_rates(700)).is_err());
+
+        set_caller(user());
+        assert_eq!(publish_rate_sheet(3 * DAY, three_month_rates(600)).unwrap_err().to_string(), "Caller does not have the Admin role");
+        assert_eq!(get_rate_sheets().len(), 1);
+    }
+}
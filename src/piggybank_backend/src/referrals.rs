@@ -0,0 +1,429 @@
+//! The referral program.
+//!
+//! Every user can create a referral code. A new user redeems someone else's
+//! code before opening their first deposit; if that first deposit meets the
+//! admin-set minimum amount and lock duration, both users are paid a bonus
+//! from the reward pool into `available_balance`. A bonus of zero turns that
+//! side off. A first deposit that falls short settles the referral without a
+//! bonus, and so does one made while rewards are paused.
+//!
+//! Codes are derived from a hash of the referrer and the time the code was
+//! created, so they cannot be guessed from one another.
+
+use crate::access::{audit, authenticated_caller, require_role, AuditEvent, Role};
+use crate::error::Error;
+use crate::money::TOKEN_UNIT;
+use crate::pause::Operation;
+use crate::products::ProductId;
+use crate::state::State;
+use crate::transactions::{self, Entry, TransactionType};
+use crate::{caller, credit_available, time, TokenDeposit, STATE};
+use candid::{CandidType, Deserialize, Principal};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+/// Product ID of the deposits that referral bonuses are credited as. It is
+/// never assigned to a catalog product, so bonuses pay the default burn fee.
+pub const BONUS_PRODUCT_ID: ProductId = ProductId::MAX - 1;
+/// Largest bonus either side of a referral can be paid.
+pub const MAX_REFERRAL_BONUS: u64 = 100 * TOKEN_UNIT;
+const CODE_PREFIX: &str = "PIGGY";
+/// Crockford's base 32, which leaves out I, L, O and U.
+const CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Amounts are in token minor units.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct ReferralSettings {
+    pub referrer_bonus: u64,
+    pub referee_bonus: u64,
+    /// Smallest first deposit, after fees, that qualifies for the bonus.
+    pub min_deposit: u64,
+    pub min_lock_nanos: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub enum ReferralStatus {
+    /// Waiting for the referee's first deposit.
+    Pending,
+    /// Both bonuses were paid, as far as the reward pool covered them.
+    Rewarded { referrer_bonus: u64, referee_bonus: u64 },
+    /// The first deposit was below the minimum amount or lock.
+    Unqualified,
+    /// The first deposit qualified but nothing was paid, because both
+    /// bonuses were off, rewards were paused or the reward pool was empty.
+    Unpaid,
+}
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct Referral {
+    pub referrer: Principal,
+    pub referee: Principal,
+    pub redeemed_at: u64,
+    pub status: ReferralStatus,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct Referrals {
+    pub settings: ReferralSettings,
+    codes: BTreeMap<String, Principal>,
+    code_of: HashMap<Principal, String>,
+    /// Keyed by referee.
+    referrals: HashMap<Principal, Referral>,
+}
+
+/// The caller's side of the referral program.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct ReferralEarnings {
+    pub code: Option<String>,
+    pub referred_by: Option<Principal>,
+    /// Users who redeemed the caller's code, oldest first.
+    pub referrals: Vec<Referral>,
+    pub earned_as_referrer: u64,
+    pub earned_as_referee: u64,
+}
+
+/// Pays out the caller's referral, if pending, on their first `deposit`.
+pub fn settle(state: &mut State, referee: Principal, deposit: &TokenDeposit, current_time: u64) {
+    let settings = state.referrals.settings.clone();
+    let Some(referral) = state.referrals.referrals.get_mut(&referee) else {
+        return;
+    };
+    if referral.status != ReferralStatus::Pending {
+        return;
+    }
+    if deposit.amount < settings.min_deposit || deposit.lock_duration_nanos < settings.min_lock_nanos {
+        referral.status = ReferralStatus::Unqualified;
+        return;
+    }
+
+    if state.pause.is_paused(Operation::Rewards) {
+        referral.status = ReferralStatus::Unpaid;
+        return;
+    }
+
+    let referrer = referral.referrer;
+    let referrer_bonus = state.reward_pool.draw_referral_bonus(settings.referrer_bonus);
+    let referee_bonus = state.reward_pool.draw_referral_bonus(settings.referee_bonus);
+    referral.status = if referrer_bonus + referee_bonus == 0 {
+        ReferralStatus::Unpaid
+    } else {
+        ReferralStatus::Rewarded {
+            referrer_bonus,
+            referee_bonus,
+        }
+    };
+    for (user, bonus) in [(referrer, referrer_bonus), (referee, referee_bonus)] {
+        if bonus > 0 {
+            credit_available(state, user, bonus, BONUS_PRODUCT_ID, current_time);
+            let entry = Entry::new(user, TransactionType::Reward, bonus, current_time).note("Referral bonus");
+            transactions::record(&mut state.transactions, entry);
+            state.notifications.rewards_credited(user, "Referral bonus", bonus, current_time);
+        }
+    }
+}
+
+/// A code for `user`: 40 bits of a hash of the user, the time and `attempt`,
+/// in base 32.
+fn referral_code(user: Principal, current_time: u64, attempt: u32) -> String {
+    let digest = Sha256::new()
+        .chain_update(user.as_slice())
+        .chain_update(current_time.to_be_bytes())
+        .chain_update(attempt.to_be_bytes())
+        .finalize();
+    let bits = digest[..5].iter().fold(0u64, |bits, &byte| bits << 8 | byte as u64);
+    let digits = (0..8).rev().map(|i| CODE_ALPHABET[(bits >> (5 * i) & 31) as usize] as char);
+    CODE_PREFIX.chars().chain(digits).collect()
+}
+
+/// Returns the caller's referral code, creating it on first use.
+#[ic_cdk::update]
+pub fn create_referral_code() -> Result<String, Error> {
+    let caller = authenticated_caller()?;
+    let current_time = time();
+
+    STATE.with(|state| {
+        let referrals = &mut state.borrow_mut().referrals;
+        if let Some(code) = referrals.code_of.get(&caller) {
+            return Ok(code.clone());
+        }
+        let code = (0..)
+            .map(|attempt| referral_code(caller, current_time, attempt))
+            .find(|code| !referrals.codes.contains_key(code))
+            .expect("referral codes exhausted");
+        referrals.codes.insert(code.clone(), caller);
+        referrals.code_of.insert(caller, code.clone());
+        Ok(code)
+    })
+}
+
+/// Redeems `code` for the caller, who must not have opened an account yet.
+#[ic_cdk::update]
+pub fn redeem_referral_code(code: String) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+    let current_time = time();
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.user_balances.contains_key(&caller) {
+            return Err("Referral codes can only be redeemed before opening an account".into());
+        }
+        let referrals = &mut state.referrals;
+        let referrer = *referrals.codes.get(code.trim()).ok_or("Referral code not found")?;
+        if referrer == caller {
+            return Err("Cannot redeem your own referral code".into());
+        }
+        // Referrals form chains, so walking up from the referrer finds the
+        // caller if the caller referred them, directly or through others
+        let mut upline = referrals.referrals.get(&referrer);
+        while let Some(referral) = upline {
+            if referral.referrer == caller {
+                return Err("Cannot redeem the code of a user you referred".into());
+            }
+            upline = referrals.referrals.get(&referral.referrer);
+        }
+        if referrals.referrals.contains_key(&caller) {
+            return Err("A referral code has already been redeemed".into());
+        }
+        referrals.referrals.insert(caller, Referral {
+            referrer,
+            referee: caller,
+            redeemed_at: current_time,
+            status: ReferralStatus::Pending,
+        });
+        Ok(())
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_referral_earnings() -> ReferralEarnings {
+    let caller = caller();
+    STATE.with(|state| {
+        let referrals = &state.borrow().referrals;
+        let mut referred: Vec<Referral> = referrals
+            .referrals
+            .values()
+            .filter(|r| r.referrer == caller)
+            .cloned()
+            .collect();
+        referred.sort_by_key(|r| (r.redeemed_at, r.referee));
+        let earned_as_referrer = referred
+            .iter()
+            .map(|r| match r.status {
+                ReferralStatus::Rewarded { referrer_bonus, .. } => referrer_bonus,
+                _ => 0,
+            })
+            .sum();
+        let own = referrals.referrals.get(&caller);
+        ReferralEarnings {
+            code: referrals.code_of.get(&caller).cloned(),
+            referred_by: own.map(|r| r.referrer),
+            referrals: referred,
+            earned_as_referrer,
+            earned_as_referee: match own.map(|r| &r.status) {
+                Some(ReferralStatus::Rewarded { referee_bonus, .. }) => *referee_bonus,
+                _ => 0,
+            },
+        }
+    })
+}
+
+/// Sets the bonuses, each at most `MAX_REFERRAL_BONUS` and zero to turn that
+/// side off, and the minimum qualifying deposit, whose lock must be one some
+/// catalog product offers.
+#[ic_cdk::update]
+pub fn set_referral_settings(settings: ReferralSettings) -> Result<(), Error> {
+    let caller = authenticated_caller()?;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        for bonus in [settings.referrer_bonus, settings.referee_bonus] {
+            if bonus > MAX_REFERRAL_BONUS {
+                return Err(format!("Referral bonuses must not exceed {}", MAX_REFERRAL_BONUS).into());
+            }
+        }
+        let products = &state.products;
+        let longest_lock = products.ids().filter_map(|id| products.get(id)).map(|p| p.duration_nanos).max();
+        if longest_lock.is_none_or(|longest| settings.min_lock_nanos > longest) {
+            return Err("Minimum lock is longer than any product".into());
+        }
+        state.referrals.settings = settings;
+        audit(&mut state, caller, AuditEvent::ReferralSettingsSet);
+        Ok(())
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_referral_settings() -> ReferralSettings {
+    STATE.with(|state| state.borrow().referrals.settings.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, as_admin, other_user as referee, user as referrer};
+    use crate::mock::set_caller;
+    use crate::pause::pause_operation;
+    use crate::{burn_tokens, get_balance, mint_tokens, LockPeriod};
+
+    fn setup() {
+        fixtures::setup();
+        as_admin(|| {
+            set_referral_settings(ReferralSettings {
+                referrer_bonus: 5_000_000,
+                referee_bonus: 2_000_000,
+                min_deposit: 100_000_000,
+                min_lock_nanos: LockPeriod::SixMonths.duration_nanos(),
+            })
+            .unwrap()
+        });
+    }
+
+    fn referred_by_code() {
+        set_caller(referrer());
+        let code = create_referral_code().unwrap();
+        set_caller(referee());
+        redeem_referral_code(code).unwrap();
+    }
+
+    #[test]
+    fn test_qualifying_first_deposit_pays_both() {
+        setup();
+        referred_by_code();
+        let deposited = mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap().deposited;
+
+        let balance = get_balance().unwrap();
+        assert_eq!(balance.available_balance, 2_000_000);
+        assert_eq!(balance.total_balance, deposited + 2_000_000);
+        assert_eq!(get_referral_earnings().earned_as_referee, 2_000_000);
+        assert_eq!(balance.deposits.last().unwrap().product_id, BONUS_PRODUCT_ID);
+        // The bonus is free to withdraw
+        assert_eq!(burn_tokens(2_000_000, None).unwrap().net_payout, 2_000_000);
+
+        set_caller(referrer());
+        assert_eq!(get_balance().unwrap().available_balance, 5_000_000);
+        let earnings = get_referral_earnings();
+        assert_eq!(earnings.earned_as_referrer, 5_000_000);
+        assert_eq!(earnings.referrals[0].referee, referee());
+
+        // Only the first deposit counts
+        set_caller(referee());
+        mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap();
+        set_caller(referrer());
+        assert_eq!(get_referral_earnings().earned_as_referrer, 5_000_000);
+    }
+
+    #[test]
+    fn test_short_first_deposit_does_not_qualify() {
+        setup();
+        referred_by_code();
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        mint_tokens(10000, LockPeriod::TwelveMonths.into(), None).unwrap();
+
+        assert_eq!(get_balance().unwrap().available_balance, 0);
+        set_caller(referrer());
+        let earnings = get_referral_earnings();
+        assert_eq!(earnings.referrals[0].status, ReferralStatus::Unqualified);
+        assert_eq!(earnings.earned_as_referrer, 0);
+    }
+
+    #[test]
+    fn test_nothing_paid_is_not_rewarded() {
+        // While rewards are paused
+        setup();
+        referred_by_code();
+        as_admin(|| pause_operation(Operation::Rewards, "Incident".to_string()).unwrap());
+        set_caller(referee());
+        mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap();
+        assert_eq!(get_balance().unwrap().available_balance, 0);
+        set_caller(referrer());
+        assert_eq!(get_referral_earnings().referrals[0].status, ReferralStatus::Unpaid);
+
+        // With both bonuses off
+        setup();
+        as_admin(|| {
+            let settings = get_referral_settings();
+            set_referral_settings(ReferralSettings { referrer_bonus: 0, referee_bonus: 0, ..settings }).unwrap()
+        });
+        referred_by_code();
+        mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap();
+        set_caller(referrer());
+        assert_eq!(get_referral_earnings().referrals[0].status, ReferralStatus::Unpaid);
+
+        // One side off pays the other, apart from interest
+        setup();
+        as_admin(|| {
+            let settings = get_referral_settings();
+            set_referral_settings(ReferralSettings { referrer_bonus: 0, ..settings }).unwrap()
+        });
+        referred_by_code();
+        mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap();
+        let status = ReferralStatus::Rewarded {
+            referrer_bonus: 0,
+            referee_bonus: 2_000_000,
+        };
+        assert_eq!(get_referral_earnings().earned_as_referee, 2_000_000);
+        set_caller(referrer());
+        assert_eq!(get_referral_earnings().referrals[0].status, status);
+        let pool = STATE.with(|state| state.borrow().reward_pool.clone());
+        assert_eq!(pool.referral_bonuses, 2_000_000);
+        assert_eq!(pool.interest_paid, 0);
+    }
+
+    #[test]
+    fn test_redeem_errors() {
+        setup();
+        let code = create_referral_code().unwrap();
+        assert_eq!(create_referral_code().unwrap(), code);
+        assert!(code.starts_with("PIGGY") && code.len() == 13);
+        assert_eq!(redeem_referral_code(code.clone()).unwrap_err().to_string(), "Cannot redeem your own referral code");
+
+        set_caller(referee());
+        assert_eq!(redeem_referral_code("NOPE".to_string()).unwrap_err().to_string(), "Referral code not found");
+        redeem_referral_code(code.clone()).unwrap();
+        assert!(redeem_referral_code(code.clone()).is_err());
+        assert_eq!(get_referral_earnings().referred_by, Some(referrer()));
+
+        // The referrer cannot be referred back by their referee
+        let referee_code = create_referral_code().unwrap();
+        assert_ne!(referee_code, code);
+        set_caller(referrer());
+        assert!(redeem_referral_code(referee_code.clone()).is_err());
+
+        // Nor by anyone further down the chain
+        set_caller(Principal::from_slice(&[6]));
+        redeem_referral_code(referee_code.clone()).unwrap();
+        let third_code = create_referral_code().unwrap();
+        set_caller(referrer());
+        assert_eq!(
+            redeem_referral_code(third_code).unwrap_err().to_string(),
+            "Cannot redeem the code of a user you referred"
+        );
+
+        set_caller(Principal::from_slice(&[7]));
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        assert_eq!(
+            redeem_referral_code(referee_code).unwrap_err().to_string(),
+            "Referral codes can only be redeemed before opening an account"
+        );
+        assert!(set_referral_settings(ReferralSettings::default()).is_err());
+    }
+
+    #[test]
+    fn test_settings_are_validated() {
+        setup();
+        let settings = get_referral_settings();
+        let set = |settings: ReferralSettings| as_admin(|| set_referral_settings(settings).map_err(|e| e.to_string()));
+
+        let error = set(ReferralSettings { referrer_bonus: MAX_REFERRAL_BONUS + 1, ..settings.clone() }).unwrap_err();
+        assert!(error.starts_with("Referral bonuses must not exceed"));
+        assert!(set(ReferralSettings { referee_bonus: MAX_REFERRAL_BONUS + 1, ..settings.clone() }).is_err());
+        set(ReferralSettings { referee_bonus: 0, ..settings.clone() }).unwrap();
+        assert_eq!(
+            set(ReferralSettings { min_lock_nanos: LockPeriod::TwelveMonths.duration_nanos() + 1, ..settings.clone() }),
+            Err("Minimum lock is longer than any product".to_string())
+        );
+        set(ReferralSettings { min_lock_nanos: LockPeriod::TwelveMonths.duration_nanos(), ..settings }).unwrap();
+        assert_eq!(get_referral_settings().min_lock_nanos, LockPeriod::TwelveMonths.duration_nanos());
+    }
+}
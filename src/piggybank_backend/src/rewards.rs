@@ -0,0 +1,186 @@
+//! Per-product reward indices.
+//!
+//! Each product keeps a cumulative index of the time during which it has
+//! accrued rewards. A deposit remembers the index value at its last accrual
+//! and earns interest at its own rate on the index delta, so accruing a
+//! deposit costs the same however long ago it was last touched, and a global
+//! accrual only has to advance one index per product.
+//!
+//! The index counts time rather than accumulating a rate because rates
+//! belong to deposits, not products: a deposit keeps the rate it was priced
+//! at for its whole term. All the deposits of a product share is which
+//! stretches of time earn interest, and that is what the index records.
+//!
+//! While rewards are paused every index is frozen, see `pause`.
+
+use crate::curve::CUSTOM_LOCK_PRODUCT_ID;
+use crate::pause::Operation;
+use crate::products::ProductId;
+use crate::state::State;
+use crate::{time, STATE};
+use candid::{CandidType, Deserialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const REWARD_ACCRUAL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Cumulative reward-accruing time of a product, in nanoseconds.
+///
+/// An index that has never been advanced has accrued since time zero, so its
+/// value equals the current time.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct RewardIndex {
+    /// Index value as of `updated_at`.
+    pub value: u64,
+    pub updated_at: u64,
+    /// Frozen at `value` since `updated_at`.
+    pub paused: bool,
+    /// Start of the current unbroken stretch of accrual: zero, or the last
+    /// time rewards resumed.
+    pub accruing_since: u64,
+}
+
+pub type RewardIndices = HashMap<ProductId, RewardIndex>;
+
+impl RewardIndex {
+    /// The index value at `time`, which may be before the last update. The
+    /// index has accrued without a break since `accruing_since`, so earlier
+    /// values back to then are recovered by counting back. Times before that
+    /// read as the value at `accruing_since`; every deposit was accrued up to
+    /// the restart, so none holds an older index value.
+    pub fn value_at(&self, time: u64) -> u64 {
+        let time = time.max(self.accruing_since);
+        if time >= self.updated_at {
+            if self.paused {
+                self.value
+            } else {
+                self.value + (time - self.updated_at)
+            }
+        } else {
+            self.value - (self.updated_at - time)
+        }
+    }
+
+    fn advance(&mut self, current_time: u64) {
+        if !self.paused && current_time > self.updated_at {
+            self.value = self.value_at(current_time);
+            self.updated_at = current_time;
+        }
+    }
+
+    /// Restarts a frozen index from `current_time`. Earlier times no longer
+    /// read correctly, so every deposit must have accrued up to then.
+    pub fn resume(&mut self, current_time: u64) {
+        if self.paused {
+            self.paused = false;
+            self.updated_at = self.updated_at.max(current_time);
+            self.accruing_since = self.updated_at;
+        }
+    }
+}
+
+/// The index of `product`, or a fresh one if it has never been advanced.
+pub fn reward_index(indices: &RewardIndices, product: ProductId) -> RewardIndex {
+    indices.get(&product).cloned().unwrap_or_default()
+}
+
+/// Advances every product's index, and the custom lock index, to
+/// `current_time`. While rewards are paused the indices are frozen there
+/// instead.
+pub fn accrue_all(state: &mut State, current_time: u64) {
+    let paused = state.pause.is_paused(Operation::Rewards);
+    let State {
+        products,
+        reward_indices,
+        ..
+    } = state;
+    for product in products.ids().chain([CUSTOM_LOCK_PRODUCT_ID]) {
+        let index = reward_indices.entry(product).or_default();
+        index.advance(current_time);
+        index.paused |= paused;
+    }
+}
+
+pub fn start_reward_timer() {
+    ic_cdk_timers::set_timer_interval(REWARD_ACCRUAL_INTERVAL, || {
+        STATE.with(|state| accrue_all(&mut state.borrow_mut(), time()))
+    });
+}
+
+/// Current index value of every product, then of the custom lock index.
+#[ic_cdk::query]
+pub fn get_reward_indices() -> Vec<(ProductId, u64)> {
+    let current_time = time();
+    STATE.with(|state| {
+        let state = state.borrow();
+        state
+            .products
+            .ids()
+            .chain([CUSTOM_LOCK_PRODUCT_ID])
+            .map(|product| (product, reward_index(&state.reward_indices, product).value_at(current_time)))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::setup;
+    use crate::mock::set_time;
+    use crate::money::interest;
+    use crate::{apply_rewards, mint_tokens, LockPeriod};
+
+    #[test]
+    fn test_index_value_at() {
+        let index = RewardIndex {
+            value: 1_000,
+            updated_at: 1_000,
+            paused: false,
+            accruing_since: 0,
+        };
+        assert_eq!(index.value_at(1_000), 1_000);
+        assert_eq!(index.value_at(1_500), 1_500);
+        assert_eq!(index.value_at(900), 900);
+        assert_eq!(RewardIndex::default().value_at(42), 42);
+
+        let mut frozen = RewardIndex {
+            paused: true,
+            ..index
+        };
+        assert_eq!(frozen.value_at(1_500), 1_000);
+        assert_eq!(frozen.value_at(900), 900);
+        frozen.advance(2_000);
+        frozen.resume(3_000);
+        assert_eq!(frozen.value_at(3_500), 1_500);
+
+        // Times before the restart read as the restart value, not counted
+        // back through the paused stretch
+        assert_eq!(frozen.value_at(3_000), 1_000);
+        assert_eq!(frozen.value_at(0), 1_000);
+    }
+
+    #[test]
+    fn test_global_accrual_matches_deposit_accrual() {
+        setup();
+        let token_amount = mint_tokens(10000, LockPeriod::TwelveMonths.into(), None).unwrap().deposited;
+
+        // Advancing the indices alone does not touch any deposit...
+        for step in 1..=10 {
+            STATE.with(|state| accrue_all(&mut state.borrow_mut(), step * 1_000_000_000_000));
+        }
+        STATE.with(|state| {
+            let state = state.borrow();
+            let index = reward_index(&state.reward_indices, LockPeriod::TwelveMonths.product_id());
+            assert_eq!(index.value, 10_000_000_000_000);
+            let deposit = &state.user_balances.values().next().unwrap().deposits[0];
+            assert_eq!(deposit.accrued_interest, 0);
+        });
+
+        // ...which later settle from the index delta
+        set_time(20_000_000_000_000);
+        assert_eq!(apply_rewards().unwrap(), interest(token_amount, 1000, 20_000_000_000_000));
+        let indices = get_reward_indices();
+        assert_eq!(indices[2], (LockPeriod::TwelveMonths.product_id(), 20_000_000_000_000));
+        assert_eq!(indices.last(), Some(&(CUSTOM_LOCK_PRODUCT_ID, 20_000_000_000_000)));
+    }
+}
@@ -0,0 +1,249 @@
+//! Canister state and its persistence across upgrades.
+//!
+//! The heap state is serialized to stable memory in `pre_upgrade` and read
+//! back in `post_upgrade`, so user balances survive `dfx deploy` upgrades.
+//!
+//! State is saved as a versioned envelope holding one Candid blob per
+//! section. Adding a new section needs no migration (a missing section loads
+//! as its default). Changing the layout of a released section bumps
+//! `SCHEMA_VERSION` and adds a step to `migrations`; a layout that has not
+//! been released yet is changed in place, along with the migration into it.
+
+mod migrations;
+
+use crate::access::{AuditEntry, Roles};
+use crate::fees::Fees;
+use crate::goals::SavingsGoal;
+use crate::loyalty::LoyaltySettings;
+use crate::notifications::Notifications;
+use crate::pause::PauseState;
+use crate::penalties::Penalties;
+use crate::products::Catalog;
+use crate::referrals::Referrals;
+use crate::transactions::Transaction;
+use crate::treasury::RewardPool;
+use crate::users::User;
+use crate::rewards::RewardIndices;
+use crate::UserBalance;
+use candid::{CandidType, Deserialize, Principal};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+/// Schema version written by this build.
+pub const SCHEMA_VERSION: u32 = 1;
+
+thread_local! {
+    pub static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct State {
+    pub user_balances: HashMap<Principal, UserBalance>,
+    pub next_deposit_id: u64,
+    pub products: Catalog,
+    pub reward_indices: RewardIndices,
+    pub roles: Roles,
+    pub audit_log: Vec<AuditEntry>,
+    pub pause: PauseState,
+    pub reward_pool: RewardPool,
+    pub fees: Fees,
+    pub penalties: Penalties,
+    pub loyalty: LoyaltySettings,
+    pub referrals: Referrals,
+    pub savings_goals: BTreeMap<u64, SavingsGoal>,
+    pub next_goal_id: u64,
+    pub users: HashMap<Principal, User>,
+    /// Lowercased username to the principal that registered it.
+    pub username_to_id: HashMap<String, Principal>,
+    pub transactions: Vec<Transaction>,
+    pub notifications: Notifications,
+}
+
+/// Serialized state sections keyed by name.
+type Sections = BTreeMap<String, Vec<u8>>;
+
+#[derive(CandidType, Deserialize)]
+struct StateEnvelope {
+    version: u32,
+    sections: Sections,
+}
+
+const USER_BALANCES: &str = "user_balances";
+const NEXT_DEPOSIT_ID: &str = "next_deposit_id";
+const PRODUCTS: &str = "products";
+const REWARD_INDICES: &str = "reward_indices";
+const ROLES: &str = "roles";
+const AUDIT_LOG: &str = "audit_log";
+const PAUSE: &str = "pause";
+const REWARD_POOL: &str = "reward_pool";
+const FEES: &str = "fees";
+const PENALTIES: &str = "penalties";
+const LOYALTY: &str = "loyalty";
+const REFERRALS: &str = "referrals";
+const SAVINGS_GOALS: &str = "savings_goals";
+const NEXT_GOAL_ID: &str = "next_goal_id";
+const USERS: &str = "users";
+const USERNAME_TO_ID: &str = "username_to_id";
+const TRANSACTIONS: &str = "transactions";
+const NOTIFICATIONS: &str = "notifications";
+
+fn put_section<T: CandidType>(sections: &mut Sections, name: &str, value: &T) {
+    let bytes = candid::encode_one(value).expect("Failed to encode state section");
+    sections.insert(name.to_string(), bytes);
+}
+
+fn get_section<T>(sections: &Sections, name: &str) -> Result<T, String>
+where
+    T: CandidType + for<'de> Deserialize<'de> + Default,
+{
+    match sections.get(name) {
+        Some(bytes) => candid::decode_one(bytes).map_err(|e| format!("Failed to decode section {}: {}", name, e)),
+        None => Ok(T::default()),
+    }
+}
+
+impl State {
+    fn to_sections(&self) -> Sections {
+        let mut sections = Sections::new();
+        put_section(&mut sections, USER_BALANCES, &self.user_balances);
+        put_section(&mut sections, NEXT_DEPOSIT_ID, &self.next_deposit_id);
+        put_section(&mut sections, PRODUCTS, &self.products);
+        put_section(&mut sections, REWARD_INDICES, &self.reward_indices);
+        put_section(&mut sections, ROLES, &self.roles);
+        put_section(&mut sections, AUDIT_LOG, &self.audit_log);
+        put_section(&mut sections, PAUSE, &self.pause);
+        put_section(&mut sections, REWARD_POOL, &self.reward_pool);
+        put_section(&mut sections, FEES, &self.fees);
+        put_section(&mut sections, PENALTIES, &self.penalties);
+        put_section(&mut sections, LOYALTY, &self.loyalty);
+        put_section(&mut sections, REFERRALS, &self.referrals);
+        put_section(&mut sections, SAVINGS_GOALS, &self.savings_goals);
+        put_section(&mut sections, NEXT_GOAL_ID, &self.next_goal_id);
+        put_section(&mut sections, USERS, &self.users);
+        put_section(&mut sections, USERNAME_TO_ID, &self.username_to_id);
+        put_section(&mut sections, TRANSACTIONS, &self.transactions);
+        put_section(&mut sections, NOTIFICATIONS, &self.notifications);
+        sections
+    }
+
+    fn from_sections(sections: &Sections) -> Result<Self, String> {
+        Ok(State {
+            user_balances: get_section(sections, USER_BALANCES)?,
+            next_deposit_id: get_section(sections, NEXT_DEPOSIT_ID)?,
+            products: get_section(sections, PRODUCTS)?,
+            reward_indices: get_section(sections, REWARD_INDICES)?,
+            roles: get_section(sections, ROLES)?,
+            audit_log: get_section(sections, AUDIT_LOG)?,
+            pause: get_section(sections, PAUSE)?,
+            reward_pool: get_section(sections, REWARD_POOL)?,
+            fees: get_section(sections, FEES)?,
+            penalties: get_section(sections, PENALTIES)?,
+            loyalty: get_section(sections, LOYALTY)?,
+            referrals: get_section(sections, REFERRALS)?,
+            savings_goals: get_section(sections, SAVINGS_GOALS)?,
+            next_goal_id: get_section(sections, NEXT_GOAL_ID)?,
+            users: get_section(sections, USERS)?,
+            username_to_id: get_section(sections, USERNAME_TO_ID)?,
+            transactions: get_section(sections, TRANSACTIONS)?,
+            notifications: get_section(sections, NOTIFICATIONS)?,
+        })
+    }
+}
+
+/// Encodes the current state into the bytes written to stable memory.
+fn save_state() -> Vec<u8> {
+    let envelope = STATE.with(|state| StateEnvelope {
+        version: SCHEMA_VERSION,
+        sections: state.borrow().to_sections(),
+    });
+    candid::encode_one(&envelope).expect("Failed to encode state")
+}
+
+/// Replaces the current state with one previously produced by `save_state`
+/// from this or any earlier schema version.
+fn restore_state(bytes: &[u8]) -> Result<(), String> {
+    let mut envelope = match candid::decode_one::<StateEnvelope>(bytes) {
+        Ok(envelope) => envelope,
+        // Written before the state was versioned
+        Err(_) => migrations::unversioned(bytes),
+    };
+    migrations::migrate(envelope.version, &mut envelope.sections)?;
+    let restored = State::from_sections(&envelope.sections)?;
+    STATE.with(|state| *state.borrow_mut() = restored);
+    Ok(())
+}
+
+#[ic_cdk::pre_upgrade]
+fn pre_upgrade() {
+    ic_cdk::storage::stable_save((save_state(),)).expect("Failed to save state to stable memory");
+}
+
+#[ic_cdk::post_upgrade]
+fn post_upgrade() {
+    // Builds that predate upgrade persistence left stable memory empty
+    if ic_cdk::api::stable::stable_size() > 0 {
+        let (bytes,): (Vec<u8>,) = ic_cdk::storage::stable_restore().expect("Failed to read state from stable memory");
+        restore_state(&bytes).expect("Failed to restore state after upgrade");
+    }
+    crate::start_timers();
+}
+
+#[ic_cdk::query]
+pub fn get_schema_version() -> u32 {
+    SCHEMA_VERSION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::setup;
+    use crate::mock::set_time;
+    use crate::{get_balance, mint_tokens, LockPeriod};
+
+    #[test]
+    fn test_upgrade_round_trip() {
+        setup();
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        set_time(1_000);
+        mint_tokens(20000, LockPeriod::TwelveMonths.into(), None).unwrap();
+        let before = get_balance().unwrap();
+
+        // Simulate an upgrade: the heap is wiped and rebuilt from stable memory
+        let bytes = save_state();
+        STATE.with(|state| *state.borrow_mut() = State::default());
+        assert!(get_balance().is_err());
+        restore_state(&bytes).unwrap();
+
+        let after = get_balance().unwrap();
+        assert_eq!(after.total_balance, before.total_balance);
+        assert_eq!(after.locked_balance, before.locked_balance);
+        assert_eq!(after.deposits.len(), 2);
+        assert_eq!(after.deposits[1].deposit_time, 1_000);
+        assert_eq!(after.deposits[1].product_id, LockPeriod::TwelveMonths.product_id());
+        STATE.with(|state| assert_eq!(state.borrow().next_deposit_id, 2));
+    }
+
+    #[test]
+    fn test_missing_section_loads_default() {
+        let envelope = StateEnvelope {
+            version: SCHEMA_VERSION,
+            sections: Sections::new(),
+        };
+        restore_state(&candid::encode_one(&envelope).unwrap()).unwrap();
+        STATE.with(|state| assert!(state.borrow().user_balances.is_empty()));
+    }
+
+    #[test]
+    fn test_restore_rejects_newer_schema() {
+        let envelope = StateEnvelope {
+            version: SCHEMA_VERSION + 1,
+            sections: Sections::new(),
+        };
+        assert!(restore_state(&candid::encode_one(&envelope).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_restore_rejects_garbage() {
+        assert!(restore_state(&[0, 1, 2, 3]).is_err());
+    }
+}
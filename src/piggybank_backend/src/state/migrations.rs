@@ -0,0 +1,203 @@
+//! Migrations between state schema versions.
+//!
+//! `MIGRATIONS[n]` upgrades the sections of a version `n` envelope to
+//! version `n + 1`. A migration decodes the affected sections with the types
+//! frozen for the old version below, converts them, and re-encodes them.
+//!
+//! Version 1 has not been released, so it has no frozen types of its own:
+//! layout changes before its release update `v0_to_v1` to produce the new
+//! layout directly.
+
+use super::{put_section, Sections, StateEnvelope, NEXT_DEPOSIT_ID, SCHEMA_VERSION, USER_BALANCES};
+use crate::{DepositStatus, LockPeriod, MaturityInstruction, TokenDeposit, UserBalance};
+use std::collections::HashMap;
+
+type Migration = fn(&mut Sections) -> Result<(), String>;
+
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [v0_to_v1];
+
+/// Section holding the whole state as written before versioning.
+const UNVERSIONED: &str = "unversioned";
+
+/// Wraps state written before versioning (a bare Candid-encoded `State`) as
+/// a version 0 envelope.
+pub(super) fn unversioned(bytes: &[u8]) -> StateEnvelope {
+    let mut sections = Sections::new();
+    sections.insert(UNVERSIONED.to_string(), bytes.to_vec());
+    StateEnvelope { version: 0, sections }
+}
+
+/// Runs every migration from `version` up to `SCHEMA_VERSION`.
+pub(super) fn migrate(version: u32, sections: &mut Sections) -> Result<(), String> {
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "State schema version {} is newer than supported version {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(sections)?;
+    }
+    Ok(())
+}
+
+/// The state as persisted before versioning.
+mod v0 {
+    use candid::{CandidType, Deserialize, Principal};
+    use std::collections::HashMap;
+
+    // Variant names are part of the Candid encoding
+    #[allow(clippy::enum_variant_names)]
+    #[derive(CandidType, Deserialize)]
+    pub enum LockPeriod {
+        ThreeMonths,
+        SixMonths,
+        TwelveMonths,
+    }
+
+    #[derive(CandidType, Deserialize)]
+    pub struct TokenDeposit {
+        pub amount: u64,
+        pub lock_period: LockPeriod,
+        pub deposit_time: u64,
+        pub interest_rate_bps: u32,
+        pub early_withdrawal_penalty_bps: u32,
+    }
+
+    #[derive(CandidType, Deserialize)]
+    pub struct UserBalance {
+        pub total_balance: u64,
+        pub locked_balance: u64,
+        pub available_balance: u64,
+        pub deposits: Vec<TokenDeposit>,
+        pub rewards_earned: u64,
+    }
+
+    #[derive(CandidType, Deserialize)]
+    pub struct State {
+        pub user_balances: HashMap<Principal, UserBalance>,
+    }
+}
+
+/// Lock periods map to the built-in products.
+fn lock_period(period: &v0::LockPeriod) -> LockPeriod {
+    match period {
+        v0::LockPeriod::ThreeMonths => LockPeriod::ThreeMonths,
+        v0::LockPeriod::SixMonths => LockPeriod::SixMonths,
+        v0::LockPeriod::TwelveMonths => LockPeriod::TwelveMonths,
+    }
+}
+
+fn deposit(id: u64, deposit: v0::TokenDeposit) -> TokenDeposit {
+    TokenDeposit {
+        id,
+        amount: deposit.amount,
+        product_id: lock_period(&deposit.lock_period).product_id(),
+        deposit_time: deposit.deposit_time,
+        lock_duration_nanos: lock_period(&deposit.lock_period).duration_nanos(),
+        interest_rate_bps: deposit.interest_rate_bps,
+        early_withdrawal_penalty_bps: deposit.early_withdrawal_penalty_bps,
+        status: DepositStatus::Locked,
+        maturity_instruction: MaturityInstruction::PayOut,
+        last_accrual_time: deposit.deposit_time,
+        // No index has been advanced yet, so each still equals the time
+        reward_index: deposit.deposit_time,
+        accrued_interest: 0,
+        accrual_remainder: 0,
+        partially_withdrawn: false,
+    }
+}
+
+/// Splits the unversioned state into sections, assigning deposit IDs in
+/// principal order and then deposit order. Every deposit was counted in
+/// `locked_balance`, so all start out locked and are matured on the next
+/// sweep, keep paying out at maturity and accrue from their deposit time.
+fn v0_to_v1(sections: &mut Sections) -> Result<(), String> {
+    let bytes = sections.remove(UNVERSIONED).ok_or("Missing unversioned state")?;
+    let state: v0::State = candid::decode_one(&bytes).map_err(|e| format!("Failed to decode unversioned state: {}", e))?;
+    let mut old: Vec<_> = state.user_balances.into_iter().collect();
+    old.sort_by_key(|(principal, _)| *principal);
+
+    let mut next_deposit_id = 0;
+    let mut user_balances = HashMap::new();
+    for (principal, balance) in old {
+        let deposits = balance
+            .deposits
+            .into_iter()
+            .map(|d| {
+                let id = next_deposit_id;
+                next_deposit_id += 1;
+                deposit(id, d)
+            })
+            .collect();
+        let balance = UserBalance {
+            total_balance: balance.total_balance,
+            locked_balance: balance.total_balance,
+            available_balance: 0,
+            deposits,
+            rewards_earned: balance.rewards_earned,
+            loyalty_bonus_earned: 0,
+            loyalty_streak: 0,
+        };
+        user_balances.insert(principal, balance);
+    }
+
+    put_section(sections, USER_BALANCES, &user_balances);
+    put_section(sections, NEXT_DEPOSIT_ID, &next_deposit_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{restore_state, STATE};
+    use candid::Principal;
+
+    #[test]
+    fn test_migrate_unversioned_state() {
+        let deposit = |deposit_time| v0::TokenDeposit {
+            amount: 120_000_000,
+            lock_period: v0::LockPeriod::SixMonths,
+            deposit_time,
+            interest_rate_bps: 700,
+            early_withdrawal_penalty_bps: 500,
+        };
+        let balance = |deposits: Vec<v0::TokenDeposit>| v0::UserBalance {
+            total_balance: 120_000_000 * deposits.len() as u64,
+            locked_balance: 120_000_000 * deposits.len() as u64,
+            available_balance: 0,
+            deposits,
+            rewards_earned: 0,
+        };
+        let first = Principal::from_slice(&[1]);
+        let second = Principal::from_slice(&[2]);
+        let legacy = v0::State {
+            user_balances: HashMap::from([
+                (second, balance(vec![deposit(7)])),
+                (first, balance(vec![deposit(42), deposit(43)])),
+            ]),
+        };
+
+        restore_state(&candid::encode_one(&legacy).unwrap()).unwrap();
+
+        STATE.with(|state| {
+            let state = state.borrow();
+            let restored = &state.user_balances[&first];
+            assert_eq!(restored.total_balance, 240_000_000);
+            assert_eq!(restored.deposits[0].deposit_time, 42);
+            assert_eq!(restored.deposits[0].product_id, LockPeriod::SixMonths.product_id());
+            assert_eq!(restored.deposits[0].lock_end_time(), 42 + LockPeriod::SixMonths.duration_nanos());
+            assert_eq!(restored.deposits[0].status, DepositStatus::Locked);
+            assert_eq!(restored.deposits[0].maturity_instruction, MaturityInstruction::PayOut);
+            assert_eq!(restored.deposits[0].last_accrual_time, 42);
+            assert_eq!(restored.deposits[0].reward_index, 42);
+            assert_eq!(restored.deposits[0].accrued_interest, 0);
+
+            // IDs follow principal order, then deposit order
+            let ids = |p: &Principal| state.user_balances[p].deposits.iter().map(|d| d.id).collect::<Vec<_>>();
+            assert_eq!(ids(&first), vec![0, 1]);
+            assert_eq!(ids(&second), vec![2]);
+            assert_eq!(state.next_deposit_id, 3);
+        });
+    }
+}
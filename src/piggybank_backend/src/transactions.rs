@@ -0,0 +1,379 @@
+//! The transaction log.
+//!
+//! Every change to a user's balance is appended here: deposits, withdrawals
+//! with their penalties and fees, interest and bonuses. A withdrawal's amount
+//! is everything taken out of the deposit, penalty and fee included. Entries
+//! are never changed or removed, and each user sees only their own.
+//!
+//! Users can also note contributions and withdrawals made outside the
+//! canister against one of their savings goals. Such entries are marked
+//! `manual`; they move no tokens, leave the goal's progress alone, since
+//! that only counts locked deposits, and are left out of goal summaries.
+
+use crate::error::Error;
+use crate::maturity::Refresh;
+use crate::withdrawal::WithdrawalQuote;
+use crate::{access, caller, time, STATE};
+use candid::{CandidType, Deserialize, Principal};
+
+const NOTE_MAX_LEN: usize = 200;
+
+#[derive(CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TransactionType {
+    Contribution,
+    Withdrawal,
+    Penalty,
+    Reward,
+    Fee,
+}
+
+/// `amount` is in token minor units.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct Transaction {
+    /// Position in the log, starting at 0.
+    pub id: u64,
+    pub user_id: Principal,
+    pub goal_id: Option<u64>,
+    pub transaction_type: TransactionType,
+    pub amount: u64,
+    pub timestamp: u64,
+    pub note: Option<String>,
+    /// Noted by the user with `add_transaction` rather than recorded by a
+    /// balance change.
+    pub manual: bool,
+}
+
+/// Totals of a goal's transactions, other than manual ones, in token minor
+/// units.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct TransactionSummary {
+    pub contributions: u64,
+    pub withdrawals: u64,
+    pub penalties: u64,
+    pub rewards: u64,
+    pub fees: u64,
+}
+
+/// An entry to append; zero amounts are skipped.
+pub struct Entry {
+    pub user_id: Principal,
+    pub goal_id: Option<u64>,
+    pub transaction_type: TransactionType,
+    pub amount: u64,
+    pub timestamp: u64,
+    pub note: Option<String>,
+}
+
+impl Entry {
+    pub fn new(user_id: Principal, transaction_type: TransactionType, amount: u64, timestamp: u64) -> Self {
+        Entry {
+            user_id,
+            goal_id: None,
+            transaction_type,
+            amount,
+            timestamp,
+            note: None,
+        }
+    }
+
+    pub fn goal(self, goal_id: Option<u64>) -> Self {
+        Entry { goal_id, ..self }
+    }
+
+    pub fn note(self, note: &str) -> Self {
+        Entry {
+            note: Some(note.to_string()),
+            ..self
+        }
+    }
+}
+
+fn append(log: &mut Vec<Transaction>, entry: Entry, manual: bool) -> Option<Transaction> {
+    if entry.amount == 0 {
+        return None;
+    }
+    let transaction = Transaction {
+        id: log.len() as u64,
+        user_id: entry.user_id,
+        goal_id: entry.goal_id,
+        transaction_type: entry.transaction_type,
+        amount: entry.amount,
+        timestamp: entry.timestamp,
+        note: entry.note,
+        manual,
+    };
+    log.push(transaction.clone());
+    Some(transaction)
+}
+
+pub fn record(log: &mut Vec<Transaction>, entry: Entry) {
+    append(log, entry, false);
+}
+
+/// Records the interest and loyalty bonuses paid by a refresh of `user`'s
+/// deposits.
+pub fn record_refresh(log: &mut Vec<Transaction>, user: Principal, refresh: &Refresh) {
+    for term in &refresh.completed {
+        record(log, Entry::new(user, TransactionType::Reward, term.interest, term.ended_at).note("Interest"));
+        record(
+            log,
+            Entry::new(user, TransactionType::Reward, term.loyalty_bonus, term.ended_at).note("Loyalty bonus"),
+        );
+    }
+}
+
+/// Records an executed withdrawal, leg by leg. `goal_of` names the goal each
+/// deposit was saved into, if any.
+pub fn record_withdrawal(
+    log: &mut Vec<Transaction>,
+    user: Principal,
+    quote: &WithdrawalQuote,
+    goal_of: impl Fn(u64) -> Option<u64>,
+    timestamp: u64,
+) {
+    for leg in &quote.legs {
+        let goal_id = goal_of(leg.deposit_id);
+        for (transaction_type, amount) in [
+            (TransactionType::Withdrawal, leg.amount),
+            (TransactionType::Penalty, leg.penalty),
+            (TransactionType::Fee, leg.fee),
+        ] {
+            record(log, Entry::new(user, transaction_type, amount, timestamp).goal(goal_id));
+        }
+    }
+}
+
+/// Notes a contribution or withdrawal made outside the canister against one
+/// of the caller's goals. The goal's progress is not changed.
+#[ic_cdk::update]
+pub fn add_transaction(
+    goal_id: u64,
+    amount: u64,
+    transaction_type: TransactionType,
+    note: Option<String>,
+) -> Result<Transaction, Error> {
+    let caller = access::authenticated_caller()?;
+    let current_time = time();
+    if amount == 0 {
+        return Err("Amount must be greater than 0".into());
+    }
+    if note.as_ref().is_some_and(|note| note.chars().count() > NOTE_MAX_LEN) {
+        return Err("Note must be at most 200 characters".into());
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state
+            .savings_goals
+            .get(&goal_id)
+            .filter(|goal| goal.user_id == caller)
+            .ok_or("Goal not found")?;
+        if !matches!(transaction_type, TransactionType::Contribution | TransactionType::Withdrawal) {
+            return Err("Only contributions and withdrawals can be recorded".into());
+        }
+        let entry = Entry {
+            note,
+            ..Entry::new(caller, transaction_type, amount, current_time).goal(Some(goal_id))
+        };
+        Ok(append(&mut state.transactions, entry, true).expect("Amount is not zero"))
+    })
+}
+
+/// One of the caller's transactions.
+#[ic_cdk::query]
+pub fn get_transaction(id: u64) -> Option<Transaction> {
+    let caller = caller();
+    STATE.with(|state| {
+        state
+            .borrow()
+            .transactions
+            .get(id as usize)
+            .filter(|transaction| transaction.user_id == caller)
+            .cloned()
+    })
+}
+
+/// The caller's transactions in the order they were logged, for one goal or
+/// all of them.
+#[ic_cdk::query]
+pub fn list_transactions(goal_id: Option<u64>) -> Vec<Transaction> {
+    let caller = caller();
+    STATE.with(|state| {
+        state
+            .borrow()
+            .transactions
+            .iter()
+            .filter(|transaction| transaction.user_id == caller)
+            .filter(|transaction| goal_id.is_none() || transaction.goal_id == goal_id)
+            .cloned()
+            .collect()
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_transaction_summary(goal_id: u64) -> Result<TransactionSummary, Error> {
+    let caller = caller();
+    STATE.with(|state| {
+        let state = state.borrow();
+        state
+            .savings_goals
+            .get(&goal_id)
+            .filter(|goal| goal.user_id == caller)
+            .ok_or("Goal not found")?;
+        let mut summary = TransactionSummary::default();
+        for transaction in state.transactions.iter().filter(|t| t.goal_id == Some(goal_id) && !t.manual) {
+            let total = match transaction.transaction_type {
+                TransactionType::Contribution => &mut summary.contributions,
+                TransactionType::Withdrawal => &mut summary.withdrawals,
+                TransactionType::Penalty => &mut summary.penalties,
+                TransactionType::Reward => &mut summary.rewards,
+                TransactionType::Fee => &mut summary.fees,
+            };
+            *total += transaction.amount;
+        }
+        Ok(summary)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::{set_fee_schedule, FeeOperation, FeeSchedule};
+    use crate::fixtures::{self, as_admin, other_user, user};
+    use crate::goals::{create_savings_goal, deposit, get_savings_goal, CreateGoalArgs, DepositArgs, GoalStatus};
+    use crate::mock::{set_caller, set_time};
+    use crate::money::{interest, penalty};
+    use crate::users::register_user;
+    use crate::{burn_tokens, get_balance, mint_tokens, LockPeriod};
+
+    fn setup() {
+        fixtures::setup();
+        register_user("saver".to_string(), "saver@example.com".to_string()).unwrap();
+    }
+
+    fn create_goal(target_amount: u64) -> u64 {
+        create_savings_goal(CreateGoalArgs {
+            name: "Bike".to_string(),
+            target_amount,
+            category: "Savings".to_string(),
+            deadline: None,
+            product: None,
+        })
+        .unwrap()
+        .id
+    }
+
+    fn types(transactions: &[Transaction]) -> Vec<(TransactionType, u64)> {
+        transactions.iter().map(|t| (t.transaction_type, t.amount)).collect()
+    }
+
+    #[test]
+    fn test_balance_changes_are_logged() {
+        setup();
+        as_admin(|| {
+            let schedule = FeeSchedule { rate_bps: 100, min_fee: 0, max_fee: None };
+            set_fee_schedule(FeeOperation::Mint, None, Some(schedule)).unwrap()
+        });
+
+        let minted = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        let quote = burn_tokens(minted.deposited / 2, None).unwrap();
+        let term = LockPeriod::ThreeMonths.duration_nanos();
+        set_time(term + 1);
+        burn_tokens(1, None).unwrap();
+
+        let remaining = minted.deposited - minted.deposited / 2;
+        assert_eq!(types(&list_transactions(None)), vec![
+            (TransactionType::Contribution, minted.deposited),
+            (TransactionType::Fee, minted.fee),
+            (TransactionType::Withdrawal, minted.deposited / 2),
+            (TransactionType::Penalty, quote.total_penalty),
+            (TransactionType::Reward, interest(remaining, 500, term)),
+            (TransactionType::Withdrawal, 1),
+        ]);
+        assert_eq!(quote.total_penalty, penalty(minted.deposited / 2, 200));
+        let reward = get_transaction(4).unwrap();
+        assert_eq!(reward.timestamp, term);
+        assert_eq!(reward.note.as_deref(), Some("Interest"));
+        assert!(!reward.manual);
+
+        // Other users see none of it
+        set_caller(other_user());
+        assert!(list_transactions(None).is_empty());
+        assert_eq!(get_transaction(0), None);
+    }
+
+    #[test]
+    fn test_goal_transactions() {
+        setup();
+        let goal_id = create_goal(1_000_000_000);
+        let goal = deposit(DepositArgs { goal_id, amount: 10000 }).unwrap();
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+        add_transaction(goal_id, 500, TransactionType::Contribution, Some("Cash".to_string())).unwrap();
+        let manual = add_transaction(goal_id, 200, TransactionType::Withdrawal, None).unwrap();
+        assert!(manual.manual);
+
+        // Manual entries leave the goal alone; withdrawing from the goal's
+        // deposit counts against it
+        assert_eq!(get_savings_goal(goal_id).unwrap(), goal);
+        let quote = crate::withdraw_deposit(goal.deposit_ids[0], 1_000_000).unwrap();
+        assert_eq!(get_savings_goal(goal_id).unwrap().current_amount, goal.current_amount - 1_000_000);
+
+        // Both are listed, but only balance changes are summed
+        assert_eq!(list_transactions(Some(goal_id)).len(), 5);
+        assert_eq!(list_transactions(None).len(), 6);
+        let summary = get_transaction_summary(goal_id).unwrap();
+        assert_eq!(summary.contributions, goal.current_amount);
+        assert_eq!(summary.withdrawals, 1_000_000);
+        assert_eq!(summary.penalties, quote.total_penalty);
+        assert_eq!(get_balance().unwrap().total_balance, 2 * goal.current_amount - 1_000_000);
+    }
+
+    #[test]
+    fn test_transaction_listing() {
+        setup();
+        let goal_id = create_goal(1_000);
+        let noted = add_transaction(goal_id, 500, TransactionType::Contribution, Some("Cash".to_string())).unwrap();
+        assert_eq!(get_transaction(noted.id), Some(noted.clone()));
+        assert_eq!(get_transaction(noted.id + 1), None);
+
+        set_caller(other_user());
+        register_user("other".to_string(), "other@example.com".to_string()).unwrap();
+        let other_goal_id = create_goal(1_000);
+        add_transaction(other_goal_id, 300, TransactionType::Contribution, None).unwrap();
+        mint_tokens(100, LockPeriod::ThreeMonths.into(), None).unwrap();
+        assert_eq!(list_transactions(None).len(), 2);
+        assert!(list_transactions(Some(goal_id)).is_empty());
+        assert_eq!(get_transaction(noted.id), None);
+
+        set_caller(user());
+        assert_eq!(list_transactions(None), vec![noted.clone()]);
+        assert_eq!(list_transactions(Some(goal_id)), vec![noted]);
+        assert_eq!(get_transaction_summary(other_goal_id + 1).unwrap_err().to_string(), "Goal not found");
+    }
+
+    #[test]
+    fn test_add_transaction_errors() {
+        setup();
+        let goal_id = create_goal(1_000);
+        assert_eq!(
+            add_transaction(goal_id, 0, TransactionType::Contribution, None).unwrap_err().to_string(),
+            "Amount must be greater than 0"
+        );
+        assert_eq!(
+            add_transaction(goal_id, 1, TransactionType::Reward, None).unwrap_err().to_string(),
+            "Only contributions and withdrawals can be recorded"
+        );
+        assert!(add_transaction(goal_id, 1, TransactionType::Contribution, Some("x".repeat(201))).is_err());
+        assert_eq!(add_transaction(7, 1, TransactionType::Contribution, None).unwrap_err().to_string(), "Goal not found");
+
+        // A manual contribution past the target does not complete the goal
+        add_transaction(goal_id, 1_000, TransactionType::Contribution, None).unwrap();
+        let goal = get_savings_goal(goal_id).unwrap();
+        assert_eq!((goal.current_amount, goal.status), (0, GoalStatus::Active));
+        assert_eq!(get_transaction_summary(goal_id).unwrap(), TransactionSummary::default());
+
+        set_caller(other_user());
+        assert!(add_transaction(goal_id, 1, TransactionType::Contribution, None).is_err());
+        assert!(get_transaction_summary(goal_id).is_err());
+    }
+}
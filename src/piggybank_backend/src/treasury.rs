@@ -0,0 +1,235 @@
+//! The reward pool that backs interest.
+//!
+//! Interest is not minted: every accrual draws from the pool, which admins
+//! fund and shares of penalties and fees top up. Interest given up by an
+//! early withdrawal goes back to the pool. When the pool runs short an
+//! accrual is paid only what is left, and the rest is recorded as a
+//! shortfall.
+
+use crate::access::{audit, authenticated_caller, require_role, AuditEvent, Role};
+use crate::error::Error;
+use crate::money::interest_with_carry;
+use crate::withdrawal::WithdrawalQuote;
+use crate::{caller, DepositStatus, STATE};
+use candid::{CandidType, Deserialize};
+
+/// Pool balance and running totals, in token minor units.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct RewardPool {
+    pub balance: u64,
+    /// Paid in by admins.
+    pub funded: u64,
+    /// Paid in from the penalty account.
+    pub penalties: u64,
+    /// Paid in from the fee treasury.
+    pub fees: u64,
+    /// Accrued interest given back by early withdrawals.
+    pub forfeited_interest: u64,
+    /// Drawn to pay interest, less the interest given back, so the balance
+    /// is always `funded + penalties + fees` less what was drawn.
+    pub interest_paid: u64,
+    /// Interest that accrued while the pool was short and was not paid.
+    pub shortfall: u64,
+    /// Drawn to pay loyalty bonuses.
+    pub loyalty_bonuses: u64,
+    /// Drawn to pay referral bonuses.
+    pub referral_bonuses: u64,
+}
+
+impl RewardPool {
+    /// Takes up to `amount` from the balance and returns what was taken.
+    fn take(&mut self, amount: u64) -> u64 {
+        let taken = amount.min(self.balance);
+        self.balance -= taken;
+        taken
+    }
+
+    /// Draws up to `amount` of interest and returns what was drawn.
+    pub fn draw(&mut self, amount: u64) -> u64 {
+        let drawn = self.take(amount);
+        self.interest_paid += drawn;
+        self.shortfall += amount - drawn;
+        drawn
+    }
+
+    /// Draws up to `amount` for a loyalty bonus and returns what was drawn.
+    /// A bonus the pool cannot cover is paid only in part.
+    pub fn draw_loyalty_bonus(&mut self, amount: u64) -> u64 {
+        let drawn = self.take(amount);
+        self.loyalty_bonuses += drawn;
+        drawn
+    }
+
+    /// Draws up to `amount` for a referral bonus and returns what was drawn.
+    pub fn draw_referral_bonus(&mut self, amount: u64) -> u64 {
+        let drawn = self.take(amount);
+        self.referral_bonuses += drawn;
+        drawn
+    }
+
+    pub fn credit_fees(&mut self, amount: u64) {
+        self.balance += amount;
+        self.fees += amount;
+    }
+
+    pub fn credit_penalties(&mut self, amount: u64) {
+        self.balance += amount;
+        self.penalties += amount;
+    }
+
+    /// Takes back the interest an executed withdrawal gave up.
+    pub fn credit_withdrawal(&mut self, quote: &WithdrawalQuote) {
+        self.balance += quote.forfeited_interest;
+        self.forfeited_interest += quote.forfeited_interest;
+        self.interest_paid -= quote.forfeited_interest;
+    }
+}
+
+/// The pool against what it still owes.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct RewardPoolStatus {
+    pub pool: RewardPool,
+    /// Interest every locked deposit will accrue by the end of its current
+    /// term and has not yet drawn.
+    pub projected_liabilities: u64,
+    /// Projected liabilities the pool balance does not cover.
+    pub uncovered: u64,
+}
+
+/// Adds `amount` to the pool and returns the new balance.
+#[ic_cdk::update]
+pub fn fund_reward_pool(amount: u64) -> Result<u64, Error> {
+    let caller = authenticated_caller()?;
+    if amount == 0 {
+        return Err("Amount must be greater than 0".into());
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        require_role(&state, &caller, Role::Admin)?;
+        let pool = &mut state.reward_pool;
+        pool.balance = pool.balance.checked_add(amount).ok_or("Amount too large")?;
+        pool.funded += amount;
+        let balance = pool.balance;
+        audit(&mut state, caller, AuditEvent::RewardPoolFunded { amount });
+        Ok(balance)
+    })
+}
+
+#[ic_cdk::query]
+pub fn get_reward_pool() -> Result<RewardPoolStatus, Error> {
+    let caller = caller();
+    STATE.with(|state| {
+        let state = state.borrow();
+        require_role(&state, &caller, Role::Auditor)?;
+        let projected_liabilities = state
+            .user_balances
+            .values()
+            .flat_map(|balance| &balance.deposits)
+            .filter(|deposit| deposit.status == DepositStatus::Locked)
+            .map(|deposit| {
+                let remaining = deposit.lock_end_time().saturating_sub(deposit.last_accrual_time);
+                interest_with_carry(deposit.amount, deposit.interest_rate_bps, remaining, deposit.accrual_remainder).0
+            })
+            .sum::<u64>();
+        Ok(RewardPoolStatus {
+            pool: state.reward_pool.clone(),
+            projected_liabilities,
+            uncovered: projected_liabilities.saturating_sub(state.reward_pool.balance),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, admin, as_admin, user, DAY};
+    use crate::mock::{set_caller, set_time};
+    use crate::money::{interest, penalty};
+    use crate::penalties::set_penalty_reward_pool_share;
+    use crate::{apply_rewards, burn_tokens, get_balance, mint_tokens, LockPeriod};
+
+    /// Starts from an empty reward pool.
+    fn setup() {
+        fixtures::setup();
+        STATE.with(|state| state.borrow_mut().reward_pool = RewardPool::default());
+    }
+
+    fn fund(amount: u64) {
+        as_admin(|| fund_reward_pool(amount).unwrap());
+    }
+
+    fn pool() -> RewardPool {
+        STATE.with(|state| state.borrow().reward_pool.clone())
+    }
+
+    #[test]
+    fn test_accrual_draws_from_pool() {
+        setup();
+        fund(1_000_000_000);
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+
+        set_time(30 * DAY);
+        let accrued = apply_rewards().unwrap();
+        assert_eq!(accrued, interest(principal, 500, 30 * DAY));
+        assert_eq!(pool().balance, 1_000_000_000 - accrued);
+        assert_eq!(pool().interest_paid, accrued);
+    }
+
+    #[test]
+    fn test_short_pool_pays_what_is_left() {
+        setup();
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+
+        // Nothing accrues from an empty pool
+        set_time(10 * DAY);
+        assert_eq!(apply_rewards().unwrap(), 0);
+        assert_eq!(pool().shortfall, interest(principal, 500, 10 * DAY));
+
+        fund(1_000);
+        set_time(20 * DAY);
+        assert_eq!(apply_rewards().unwrap(), 1_000);
+        assert_eq!(pool().balance, 0);
+        assert_eq!(get_balance().unwrap().deposits[0].accrued_interest, 1_000);
+    }
+
+    #[test]
+    fn test_withdrawals_refill_pool() {
+        setup();
+        fund(1_000_000_000);
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        set_time(30 * DAY);
+        let accrued = apply_rewards().unwrap();
+
+        // Burning everything early forfeits the interest; the penalty goes to
+        // the penalty account, which passes on a quarter
+        as_admin(|| set_penalty_reward_pool_share(2_500).unwrap());
+        burn_tokens(principal, None).unwrap();
+        let pool = pool();
+        assert_eq!(pool.penalties, penalty(principal, 200) / 4);
+        assert_eq!(pool.forfeited_interest, accrued);
+        assert_eq!(pool.interest_paid, 0);
+        assert_eq!(pool.balance, 1_000_000_000 + penalty(principal, 200) / 4);
+        let drawn = pool.interest_paid + pool.loyalty_bonuses + pool.referral_bonuses;
+        assert_eq!(pool.balance, pool.funded + pool.penalties + pool.fees - drawn);
+    }
+
+    #[test]
+    fn test_reward_pool_status() {
+        setup();
+        fund(1_000);
+        let principal = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        let liabilities = interest(principal, 500, LockPeriod::ThreeMonths.duration_nanos());
+
+        assert!(get_reward_pool().is_err());
+        set_caller(admin());
+        let status = get_reward_pool().unwrap();
+        assert_eq!(status.pool.balance, 1_000);
+        assert_eq!(status.projected_liabilities, liabilities);
+        assert_eq!(status.uncovered, liabilities - 1_000);
+
+        assert!(fund_reward_pool(0).is_err());
+        set_caller(user());
+        assert_eq!(fund_reward_pool(1).unwrap_err().to_string(), "Caller does not have the Admin role");
+    }
+}
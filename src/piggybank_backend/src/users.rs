@@ -0,0 +1,185 @@
+//! User profiles.
+//!
+//! A principal can register one profile with a unique username and a contact
+//! email. Usernames are matched case-insensitively through the
+//! `username_to_id` index. Registration is needed to create savings goals;
+//! holding a balance is not.
+
+use crate::access::{has_role, Role};
+use crate::error::Error;
+use crate::state::State;
+use crate::{access, caller, time, STATE};
+use candid::{CandidType, Deserialize, Principal};
+
+const USERNAME_MAX_LEN: usize = 32;
+const EMAIL_MAX_LEN: usize = 254;
+
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct User {
+    pub id: Principal,
+    pub username: String,
+    pub email: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Usernames are 3 to 32 letters, digits or underscores.
+fn validate_username(username: &str) -> Result<(), String> {
+    if username.len() < 3 || username.len() > USERNAME_MAX_LEN {
+        return Err("Username must be between 3 and 32 characters".to_string());
+    }
+    if !username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err("Username may only contain letters, digits and underscores".to_string());
+    }
+    Ok(())
+}
+
+/// A plain shape check: one `@` between a non-empty local part and a dotted
+/// domain, with no whitespace.
+fn validate_email(email: &str) -> Result<(), String> {
+    let valid = email.len() <= EMAIL_MAX_LEN
+        && !email.chars().any(char::is_whitespace)
+        && email.split_once('@').is_some_and(|(local, domain)| {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() > 1
+                && domain.split('.').all(|label| !label.is_empty())
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err("Invalid email address".to_string())
+    }
+}
+
+/// Fails unless `principal` has registered a profile.
+pub fn require_registered(state: &State, principal: &Principal) -> Result<(), String> {
+    if state.users.contains_key(principal) {
+        Ok(())
+    } else {
+        Err("User not registered".to_string())
+    }
+}
+
+#[ic_cdk::update]
+pub fn register_user(username: String, email: String) -> Result<User, Error> {
+    let caller = access::authenticated_caller()?;
+    let current_time = time();
+    let username = username.trim().to_string();
+    let email = email.trim().to_string();
+    validate_username(&username)?;
+    validate_email(&email)?;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.users.contains_key(&caller) {
+            return Err("User already registered".into());
+        }
+        let key = username.to_lowercase();
+        if state.username_to_id.contains_key(&key) {
+            return Err("Username is already taken".into());
+        }
+        let user = User {
+            id: caller,
+            username,
+            email,
+            created_at: current_time,
+            updated_at: current_time,
+        };
+        state.username_to_id.insert(key, caller);
+        state.users.insert(caller, user.clone());
+        Ok(user)
+    })
+}
+
+/// Changes the caller's email.
+#[ic_cdk::update]
+pub fn update_user(email: String) -> Result<User, Error> {
+    let caller = access::authenticated_caller()?;
+    let email = email.trim().to_string();
+    validate_email(&email)?;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let user = state.users.get_mut(&caller).ok_or("User not found")?;
+        user.email = email;
+        user.updated_at = time();
+        Ok(user.clone())
+    })
+}
+
+/// The profile of `principal`, visible to its owner and to auditors.
+#[ic_cdk::query]
+pub fn get_user(principal: Principal) -> Option<User> {
+    let caller = caller();
+    STATE.with(|state| {
+        let state = state.borrow();
+        if caller != principal && !has_role(&state, &caller, Role::Auditor) {
+            return None;
+        }
+        state.users.get(&principal).cloned()
+    })
+}
+
+/// The principal registered under `username`, if any.
+#[ic_cdk::query]
+pub fn find_user(username: String) -> Option<Principal> {
+    STATE.with(|state| state.borrow().username_to_id.get(&username.trim().to_lowercase()).copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{admin, other_user, setup, user};
+    use crate::mock::{set_caller, set_time};
+
+    #[test]
+    fn test_register_and_update() {
+        setup();
+        let registered = register_user("Saver_1".to_string(), "saver@example.com".to_string()).unwrap();
+        assert_eq!(registered.id, user());
+        assert_eq!((registered.username.as_str(), registered.email.as_str()), ("Saver_1", "saver@example.com"));
+        assert_eq!(get_user(user()), Some(registered.clone()));
+        assert_eq!(find_user("saver_1".to_string()), Some(user()));
+
+        set_time(1_000);
+        let updated = update_user("new@example.org".to_string()).unwrap();
+        assert_eq!(updated.email, "new@example.org");
+        assert_eq!(updated.created_at, 0);
+        assert_eq!(updated.updated_at, 1_000);
+        assert_eq!(update_user("not-an-email".to_string()).unwrap_err().to_string(), "Invalid email address");
+
+        // Profiles are visible to their owner and to auditors only
+        set_caller(other_user());
+        assert_eq!(get_user(user()), None);
+        assert_eq!(update_user("other@example.com".to_string()).unwrap_err().to_string(), "User not found");
+        set_caller(admin());
+        assert_eq!(get_user(user()), Some(updated));
+    }
+
+    #[test]
+    fn test_register_errors() {
+        setup();
+        register_user("saver".to_string(), "saver@example.com".to_string()).unwrap();
+        assert_eq!(
+            register_user("other".to_string(), "saver@example.com".to_string()).unwrap_err().to_string(),
+            "User already registered"
+        );
+
+        set_caller(other_user());
+        assert_eq!(
+            register_user("SAVER".to_string(), "other@example.com".to_string()).unwrap_err().to_string(),
+            "Username is already taken"
+        );
+        assert!(register_user("ab".to_string(), "other@example.com".to_string()).is_err());
+        assert!(register_user("bad name".to_string(), "other@example.com".to_string()).is_err());
+        for email in ["", "a@b", "@example.com", "a@@example.com", "a b@example.com", "a@example..com"] {
+            assert!(register_user("other".to_string(), email.to_string()).is_err(), "{}", email);
+        }
+        assert_eq!(get_user(other_user()), None);
+
+        set_caller(Principal::anonymous());
+        assert_eq!(register_user("anon".to_string(), "anon@example.com".to_string()).unwrap_err(), Error::Unauthenticated);
+        assert_eq!(update_user("anon@example.com".to_string()).unwrap_err(), Error::Unauthenticated);
+    }
+}
@@ -0,0 +1,316 @@
+//! Planning and executing withdrawals across a user's deposits.
+//!
+//! Every withdrawal is first planned into a `WithdrawalQuote` listing the
+//! deposits it touches; the update methods then execute that same quote, so
+//! `quote_withdrawal` is guaranteed to match what `burn_tokens` pays out.
+
+use crate::error::Error;
+use crate::fees::{FeeOperation, Fees};
+use crate::pause::{self, Operation};
+use crate::products::ProductId;
+use crate::{caller, maturity, time, DepositStatus, UserBalance, STATE};
+use candid::{CandidType, Deserialize};
+
+/// Order in which a multi-deposit withdrawal draws from deposits.
+#[derive(CandidType, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum WithdrawalStrategy {
+    /// Matured deposits first, then locked deposits closest to maturity.
+    MaturedFirst,
+    /// Lowest cost per token first, counting the penalty, fee and forfeited
+    /// interest, so the user gives up the least possible.
+    #[default]
+    LeastPenalty,
+    /// Oldest deposits first.
+    Fifo,
+    /// Newest deposits first.
+    Lifo,
+}
+
+/// The part of a withdrawal taken from one deposit.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct WithdrawalLeg {
+    pub deposit_id: u64,
+    pub product_id: ProductId,
+    pub amount: u64,
+    pub penalty: u64,
+    pub fee: u64,
+    pub forfeited_interest: u64,
+}
+
+/// All amounts are in token minor units.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct WithdrawalQuote {
+    pub legs: Vec<WithdrawalLeg>,
+    pub amount: u64,
+    pub total_penalty: u64,
+    pub total_fee: u64,
+    pub forfeited_interest: u64,
+    pub net_payout: u64,
+}
+
+impl WithdrawalQuote {
+    fn new(legs: Vec<WithdrawalLeg>) -> Self {
+        let amount = legs.iter().map(|l| l.amount).sum();
+        let total_penalty = legs.iter().map(|l| l.penalty).sum();
+        let total_fee = legs.iter().map(|l| l.fee).sum();
+        let forfeited_interest = legs.iter().map(|l| l.forfeited_interest).sum();
+        WithdrawalQuote {
+            legs,
+            amount,
+            total_penalty,
+            total_fee,
+            forfeited_interest,
+            net_payout: amount - total_penalty - total_fee,
+        }
+    }
+}
+
+/// What the user gives up by taking `leg`.
+fn cost(leg: &WithdrawalLeg) -> u64 {
+    leg.penalty + leg.fee + leg.forfeited_interest
+}
+
+/// The fee is charged on what the penalty leaves.
+fn leg(balance: &UserBalance, index: usize, amount: u64, fees: &Fees, current_time: u64) -> WithdrawalLeg {
+    let deposit = &balance.deposits[index];
+    let penalty = deposit.early_withdrawal_penalty(amount, current_time);
+    WithdrawalLeg {
+        deposit_id: deposit.id,
+        product_id: deposit.product_id,
+        amount,
+        penalty,
+        fee: fees.fee(FeeOperation::Burn, deposit.product_id, amount).min(amount - penalty),
+        forfeited_interest: deposit.forfeited_interest(amount),
+    }
+}
+
+/// Plans taking `amount` from the user's deposits in the order given by
+/// `strategy`.
+pub fn plan(
+    balance: &UserBalance,
+    amount: u64,
+    strategy: WithdrawalStrategy,
+    fees: &Fees,
+    current_time: u64,
+) -> Result<WithdrawalQuote, String> {
+    if amount == 0 {
+        return Err("Amount must be greater than 0".to_string());
+    }
+    if balance.total_balance < amount {
+        return Err("Insufficient balance".to_string());
+    }
+
+    let deposits = &balance.deposits;
+    let mut order: Vec<usize> = (0..deposits.len()).collect();
+    match strategy {
+        WithdrawalStrategy::MaturedFirst => {
+            order.sort_by_key(|&i| (deposits[i].lock_end_time(), deposits[i].id));
+        }
+        WithdrawalStrategy::LeastPenalty => {
+            // Costs grow linearly with the amount taken, apart from fee
+            // minimums and caps, so drawing from the cheapest deposits per
+            // token first minimises the total.
+            let whole: Vec<WithdrawalLeg> =
+                (0..deposits.len()).map(|i| leg(balance, i, deposits[i].amount, fees, current_time)).collect();
+            order.sort_by(|&a, &b| {
+                let per_token = |x: usize, y: usize| cost(&whole[x]) as u128 * whole[y].amount as u128;
+                per_token(a, b)
+                    .cmp(&per_token(b, a))
+                    .then((deposits[a].lock_end_time(), deposits[a].id).cmp(&(deposits[b].lock_end_time(), deposits[b].id)))
+            });
+        }
+        WithdrawalStrategy::Fifo => order.sort_by_key(|&i| (deposits[i].deposit_time, deposits[i].id)),
+        WithdrawalStrategy::Lifo => {
+            order.sort_by_key(|&i| std::cmp::Reverse((deposits[i].deposit_time, deposits[i].id)));
+        }
+    }
+
+    let mut legs = Vec::new();
+    let mut remaining_amount = amount;
+    for index in order {
+        if remaining_amount == 0 {
+            break;
+        }
+        let amount_to_withdraw = std::cmp::min(remaining_amount, deposits[index].amount);
+        legs.push(leg(balance, index, amount_to_withdraw, fees, current_time));
+        remaining_amount -= amount_to_withdraw;
+    }
+
+    Ok(WithdrawalQuote::new(legs))
+}
+
+/// Plans taking `amount` from a single deposit.
+pub fn plan_for_deposit(
+    balance: &UserBalance,
+    deposit_id: u64,
+    amount: u64,
+    fees: &Fees,
+    current_time: u64,
+) -> Result<WithdrawalQuote, String> {
+    if amount == 0 {
+        return Err("Amount must be greater than 0".to_string());
+    }
+    let index = balance
+        .deposits
+        .iter()
+        .position(|d| d.id == deposit_id)
+        .ok_or("Deposit not found")?;
+    if balance.deposits[index].amount < amount {
+        return Err("Insufficient deposit balance".to_string());
+    }
+
+    Ok(WithdrawalQuote::new(vec![leg(balance, index, amount, fees, current_time)]))
+}
+
+/// Applies a quote produced by `plan` or `plan_for_deposit` against the
+/// same balance.
+pub fn execute(balance: &mut UserBalance, quote: &WithdrawalQuote) {
+    for leg in &quote.legs {
+        if let Some(deposit) = balance.deposits.iter_mut().find(|d| d.id == leg.deposit_id) {
+            deposit.amount -= leg.amount;
+            deposit.accrued_interest -= leg.forfeited_interest;
+            match deposit.status {
+                DepositStatus::Locked => {
+                    balance.locked_balance -= leg.amount;
+                    deposit.partially_withdrawn = true;
+                    balance.loyalty_streak = 0;
+                }
+                DepositStatus::Matured => balance.available_balance -= leg.amount,
+            }
+        }
+    }
+    balance.total_balance -= quote.amount;
+    balance.deposits.retain(|d| d.amount > 0);
+}
+
+/// Previews what `burn_tokens` would pay out for `token_amount` without
+/// changing any balances. Fails like `burn_tokens` while withdrawals are
+/// paused.
+#[ic_cdk::query]
+pub fn quote_withdrawal(token_amount: u64, strategy: Option<WithdrawalStrategy>) -> Result<WithdrawalQuote, Error> {
+    let caller = caller();
+    let current_time = time();
+    pause::check(Operation::Withdrawals)?;
+
+    STATE.with(|state| {
+        let state = state.borrow();
+        let user_balance = maturity::refreshed_balance(&state, &caller, current_time).ok_or("User not found")?;
+        Ok(plan(&user_balance, token_amount, strategy.unwrap_or_default(), &state.fees, current_time)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::setup;
+    use crate::mock::set_time;
+    use crate::money::{interest, penalty, NANOS_PER_YEAR};
+    use crate::{burn_tokens, get_balance, mint_tokens, LockPeriod};
+
+    #[test]
+    fn test_quote_matches_burn() {
+        setup();
+        let three_month = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        mint_tokens(10000, LockPeriod::TwelveMonths.into(), None).unwrap();
+        set_time(NANOS_PER_YEAR / 10);
+
+        let amount = three_month + three_month / 2;
+        let quote = quote_withdrawal(amount, None).unwrap();
+        assert_eq!(quote.legs.len(), 2);
+        assert_eq!(quote.legs[0], WithdrawalLeg {
+            deposit_id: 0,
+            product_id: LockPeriod::ThreeMonths.product_id(),
+            amount: three_month,
+            penalty: three_month / 50,
+            fee: 0,
+            forfeited_interest: three_month / 200,
+        });
+        assert_eq!(quote.legs[1].deposit_id, 1);
+        assert_eq!(quote.legs[1].amount, three_month / 2);
+        assert_eq!(quote.legs[1].penalty, three_month / 20);
+        assert_eq!(quote.legs[1].forfeited_interest, three_month / 200);
+        assert_eq!(quote.total_penalty, three_month / 50 + three_month / 20);
+        assert_eq!(quote.net_payout, amount - quote.total_penalty);
+
+        // Quoting leaves balances untouched
+        assert_eq!(get_balance().unwrap().total_balance, 2 * three_month);
+
+        assert_eq!(burn_tokens(amount, None).unwrap().net_payout, quote.net_payout);
+        assert_eq!(get_balance().unwrap().total_balance, three_month / 2);
+    }
+
+    #[test]
+    fn test_quote_errors() {
+        setup();
+        assert_eq!(quote_withdrawal(1, None).unwrap_err().to_string(), "User not found");
+
+        let token_amount = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        assert!(quote_withdrawal(0, None).is_err());
+        assert_eq!(quote_withdrawal(token_amount + 1, None).unwrap_err().to_string(), "Insufficient balance");
+    }
+
+    #[test]
+    fn test_quote_after_lock_period() {
+        setup();
+        let token_amount = mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap().deposited;
+        set_time(LockPeriod::ThreeMonths.duration_nanos());
+
+        let quote = quote_withdrawal(token_amount, Some(WithdrawalStrategy::Fifo)).unwrap();
+        assert_eq!(quote.total_penalty, 0);
+        assert_eq!(quote.forfeited_interest, 0);
+        assert_eq!(quote.net_payout, token_amount);
+    }
+
+    fn touched(quote: &WithdrawalQuote) -> Vec<u64> {
+        quote.legs.iter().map(|l| l.deposit_id).collect()
+    }
+
+    #[test]
+    fn test_strategies() {
+        setup();
+        let unit = mint_tokens(10000, LockPeriod::TwelveMonths.into(), None).unwrap().deposited; // id 0, locked at 10%
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap(); // id 1, matures first
+        set_time(LockPeriod::ThreeMonths.duration_nanos() / 2);
+        mint_tokens(10000, LockPeriod::SixMonths.into(), None).unwrap(); // id 2, locked at 5%
+        set_time(LockPeriod::ThreeMonths.duration_nanos());
+
+        let quote = |strategy| quote_withdrawal(2 * unit, Some(strategy)).unwrap();
+
+        // The matured deposit has been paid its interest, so less is taken
+        // from the 6-month deposit
+        let three_month_interest = interest(unit, 500, LockPeriod::ThreeMonths.duration_nanos());
+        let least_penalty = quote(WithdrawalStrategy::LeastPenalty);
+        assert_eq!(touched(&least_penalty), vec![1, 2]);
+        assert_eq!(least_penalty.total_penalty, penalty(unit - three_month_interest, 500));
+
+        let matured_first = quote(WithdrawalStrategy::MaturedFirst);
+        assert_eq!(touched(&matured_first), vec![1, 2]);
+
+        let fifo = quote(WithdrawalStrategy::Fifo);
+        assert_eq!(touched(&fifo), vec![0, 1]);
+        assert_eq!(fifo.total_penalty, unit / 10);
+
+        let lifo = quote(WithdrawalStrategy::Lifo);
+        assert_eq!(touched(&lifo), vec![2, 1]);
+
+        // The default never costs more than any other strategy
+        let default = quote_withdrawal(2 * unit, None).unwrap();
+        assert_eq!(default, least_penalty);
+        for other in [matured_first, fifo, lifo] {
+            assert!(default.total_penalty <= other.total_penalty);
+        }
+    }
+
+    #[test]
+    fn test_burn_uses_strategy() {
+        setup();
+        let unit = mint_tokens(10000, LockPeriod::TwelveMonths.into(), None).unwrap().deposited;
+        mint_tokens(10000, LockPeriod::ThreeMonths.into(), None).unwrap();
+
+        // LIFO breaks the 3-month lock at 2% rather than the 12-month at 10%
+        assert_eq!(burn_tokens(unit, Some(WithdrawalStrategy::Lifo)).unwrap().net_payout, unit - unit / 50);
+        let balance = get_balance().unwrap();
+        assert_eq!(balance.deposits.len(), 1);
+        assert_eq!(balance.deposits[0].product_id, LockPeriod::TwelveMonths.product_id());
+    }
+}
@@ -1,8 +1,28 @@
 import React, { useState, useEffect } from 'react';
 import { useAuth } from '../context/AuthContext';
-import { backendService } from '../services/backendService';
+import { backendService, formatTokens, TOKEN_UNIT } from '../services/backendService';
 import './Dashboard.css';
 
+const LOCK_PERIODS = ['ThreeMonths', 'SixMonths', 'TwelveMonths'];
+
+// Product ID carried by deposits locked until a custom date.
+const CUSTOM_LOCK_PRODUCT_ID = 2n ** 64n - 1n;
+
+// The built-in lock periods are products 0 to 2; other deposits show their
+// product ID.
+const lockPeriodOf = (deposit) => {
+  if (deposit.product_id === CUSTOM_LOCK_PRODUCT_ID) {
+    return 'Custom date';
+  }
+  return LOCK_PERIODS[Number(deposit.product_id)] ?? `Product ${deposit.product_id}`;
+};
+
+// Rates are in basis points.
+const formatRate = (bps) => `${bps / 100}%`;
+
+// Penalty for breaking a deposit's lock, in token minor units.
+const penaltyOf = (deposit) => deposit.amount * BigInt(deposit.early_withdrawal_penalty_bps) / 10000n;
+
 const Dashboard = () => {
   const { principal, logout } = useAuth();
   const [balance, setBalance] = useState(null);
@@ -28,8 +48,8 @@ const Dashboard = () => {
         const deposits = userBalance.deposits.map(deposit => ({
           type: 'Deposit',
           amount: Number(deposit.amount),
-          lockPeriod: Object.keys(deposit.lock_period)[0],
-          interestRate: `${deposit.interest_rate}%`,
+          lockPeriod: lockPeriodOf(deposit),
+          interestRate: formatRate(deposit.interest_rate_bps),
           date: new Date(Number(deposit.deposit_time) / 1000000),
           isEarlyWithdrawal: false
         }));
@@ -74,44 +94,10 @@ const Dashboard = () => {
         { [selectedLockPeriod]: null }
       );
 
-      // Add to transaction history
-      const transaction = {
-        type: 'Deposit',
-        amount: Number(depositAmount),
-        lockPeriod: selectedLockPeriod,
-        interestRate: getInterestRate(selectedLockPeriod),
-        date: new Date(),
-        isEarlyWithdrawal: false
-      };
-      setTransactionHistory(prev => [transaction, ...prev]);
-
-      // Update balance
-      const currentBalance = balance || {
-        total_balance: BigInt(0),
-        locked_balance: BigInt(0),
-        available_balance: BigInt(0),
-        deposits: [],
-        rewards_earned: BigInt(0)
-      };
-
-      setBalance({
-        ...currentBalance,
-        total_balance: currentBalance.total_balance + amount,
-        locked_balance: currentBalance.locked_balance + amount,
-        available_balance: currentBalance.available_balance + amount,
-        deposits: [
-          ...currentBalance.deposits,
-          {
-            amount: amount,
-            lock_period: { [selectedLockPeriod]: null },
-            deposit_time: BigInt(Date.now() * 1000000),
-            interest_rate: Number(getInterestRate(selectedLockPeriod).replace('%', '')),
-            early_withdrawal_penalty: BigInt(0)
-          }
-        ]
-      });
-
+      // The backend prices the deposit, so reload it rather than guessing
       setDepositAmount('');
+      await fetchBalance();
+      await fetchTransactionHistory();
     } catch (err) {
       console.error('Error depositing:', err);
       setError('Failed to deposit. Please try again.');
@@ -131,8 +117,8 @@ const Dashboard = () => {
       setLoading(true);
       setError('');
       
-      // Convert to BigInt without any multiplication
-      const withdrawAmountInBigInt = BigInt(Math.floor(Number(withdrawAmount)));
+      // Convert whole tokens to minor units
+      const withdrawAmountInBigInt = BigInt(Math.round(Number(withdrawAmount) * TOKEN_UNIT));
 
       // Check total balance instead of available balance
       if (!balance?.total_balance || balance.total_balance < withdrawAmountInBigInt) {
@@ -153,7 +139,7 @@ const Dashboard = () => {
         // Add withdrawal to transaction history
         const transaction = {
           type: 'Withdrawal',
-          amount: Number(withdrawAmount),
+          amount: Number(withdrawAmountInBigInt),
           lockPeriod: '-',
           interestRate: '-',
           date: new Date(),
@@ -187,7 +173,7 @@ const Dashboard = () => {
       setError('');
       
       const withdrawAmountInBigInt = BigInt(selectedDeposit.amount);
-      const penaltyAmount = BigInt(Math.floor(Number(selectedDeposit.amount) * 0.1));
+      const penaltyAmount = penaltyOf(selectedDeposit);
       const totalWithdrawAmount = withdrawAmountInBigInt + penaltyAmount;
       
       if (!balance?.total_balance || balance.total_balance < totalWithdrawAmount) {
@@ -204,11 +190,12 @@ const Dashboard = () => {
         const transaction = {
           type: 'Withdrawal',
           amount: Number(selectedDeposit.amount),
-          lockPeriod: Object.keys(selectedDeposit.lock_period)[0],
-          interestRate: getInterestRate(Object.keys(selectedDeposit.lock_period)[0]),
+          lockPeriod: lockPeriodOf(selectedDeposit),
+          interestRate: formatRate(selectedDeposit.interest_rate_bps),
           date: new Date(),
           isEarlyWithdrawal: true,
-          penalty: Number(penaltyAmount)
+          penalty: Number(penaltyAmount),
+          penaltyRate: formatRate(selectedDeposit.early_withdrawal_penalty_bps)
         };
         setTransactionHistory(prev => [transaction, ...prev]);
 
@@ -243,29 +230,16 @@ const Dashboard = () => {
 
   const formatAmount = (amount) => {
     if (!amount) return '0';
-    // Convert BigInt to number and format with 2 decimal places
-    return Number(amount).toFixed(2);
-  };
-
-  const getInterestRate = (period) => {
-    switch (period) {
-      case 'ThreeMonths':
-        return '5%';
-      case 'SixMonths':
-        return '8%';
-      case 'TwelveMonths':
-        return '12%';
-      default:
-        return '0%';
-    }
+    // Amounts are token minor units; show whole tokens with 2 decimal places
+    return formatTokens(amount);
   };
 
   const calculatePenalty = (deposit) => {
-    return (Number(deposit.amount) * 0.1).toFixed(2);
+    return formatAmount(penaltyOf(deposit));
   };
 
   const calculateWithdrawAmount = (deposit) => {
-    return (Number(deposit.amount) * 0.9).toFixed(2);
+    return formatAmount(deposit.amount - penaltyOf(deposit));
   };
 
   return (
@@ -314,6 +288,12 @@ const Dashboard = () => {
               {loading ? 'Loading...' : `₹${formatAmount(balance?.rewards_earned)}`}
             </div>
           </div>
+          <div className="balance-card">
+            <h2>Loyalty Bonus</h2>
+            <div className="balance-amount">
+              {loading ? 'Loading...' : `₹${formatAmount(balance?.loyalty_bonus_earned)}`}
+            </div>
+          </div>
         </div>
 
         <div className="deposit-section">
@@ -334,8 +314,8 @@ const Dashboard = () => {
               disabled={loading}
             >
               <option value="ThreeMonths">3 Months (5% APY)</option>
-              <option value="SixMonths">6 Months (8% APY)</option>
-              <option value="TwelveMonths">12 Months (12% APY)</option>
+              <option value="SixMonths">6 Months (7% APY)</option>
+              <option value="TwelveMonths">12 Months (10% APY)</option>
             </select>
             <button
               type="submit"
@@ -367,7 +347,7 @@ const Dashboard = () => {
             </button>
           </form>
           <p className="warning-text">
-            Note: Early withdrawal will incur a 10% penalty
+            Note: Early withdrawal incurs the deposit's early withdrawal penalty
           </p>
         </div>
 
@@ -399,7 +379,7 @@ const Dashboard = () => {
                   <td>
                     {transaction.isEarlyWithdrawal && (
                       <span className="early-withdrawal-status">
-                        Early Withdrawal (10% Penalty: ₹{formatAmount(transaction.penalty)})
+                        Early Withdrawal ({transaction.penaltyRate} Penalty: ₹{formatAmount(transaction.penalty)})
                       </span>
                     )}
                   </td>
@@ -417,11 +397,15 @@ const Dashboard = () => {
             <p>You are attempting to withdraw before the lock period ends.</p>
             <div className="warning-details">
               <p>Original Amount: ₹{formatAmount(selectedDeposit.amount)}</p>
-              <p>Lock Period: {Object.keys(selectedDeposit.lock_period)[0]}</p>
-              <p>Interest Rate: {getInterestRate(Object.keys(selectedDeposit.lock_period)[0])}</p>
-              <p className="penalty">Early Withdrawal Penalty (10%): ₹{calculatePenalty(selectedDeposit)}</p>
+              <p>Lock Period: {lockPeriodOf(selectedDeposit)}</p>
+              <p>Interest Rate: {formatRate(selectedDeposit.interest_rate_bps)}</p>
+              <p className="penalty">
+                Early Withdrawal Penalty ({formatRate(selectedDeposit.early_withdrawal_penalty_bps)}): ₹{calculatePenalty(selectedDeposit)}
+              </p>
               <p className="withdraw-amount">Amount After Penalty: ₹{calculateWithdrawAmount(selectedDeposit)}</p>
-              <p className="warning-text">Warning: Early withdrawal will result in a 10% penalty of your deposit amount.</p>
+              <p className="warning-text">
+                Warning: Early withdrawal will result in a {formatRate(selectedDeposit.early_withdrawal_penalty_bps)} penalty of your deposit amount.
+              </p>
             </div>
             <div className="modal-buttons">
               <button onClick={() => setShowWarningModal(false)} className="cancel-btn">
@@ -1,5 +1,6 @@
 import React, { useState, useEffect } from 'react';
-import { canister } from '../declarations/piggybank_backend';
+import { piggybank_backend as canister } from '../declarations/piggybank_backend';
+import { errorMessage, formatTokens, TOKEN_UNIT } from '../services/backendService';
 
 function TokenManager() {
   const [balance, setBalance] = useState(null);
@@ -15,10 +16,10 @@ function TokenManager() {
   const fetchBalance = async () => {
     try {
       const result = await canister.get_balance();
-      if (result.Ok) {
+      if ('Ok' in result) {
         setBalance(result.Ok);
       } else {
-        setError(result.Err);
+        setError(errorMessage(result.Err));
       }
     } catch (err) {
       setError('Failed to fetch balance');
@@ -33,12 +34,13 @@ function TokenManager() {
         return;
       }
 
-      const result = await canister.mint_tokens(inrAmount, { [lockPeriod]: null });
-      if (result.Ok) {
-        setSuccess(`Successfully minted ${result.Ok} tokens`);
+      const result = await canister.mint_tokens(BigInt(inrAmount), { [lockPeriod]: null }, []);
+      if ('Ok' in result) {
+        const { deposited, fee } = result.Ok;
+        setSuccess(`Successfully minted ${formatTokens(deposited)} tokens (fee ${formatTokens(fee)})`);
         fetchBalance();
       } else {
-        setError(result.Err);
+        setError(errorMessage(result.Err));
       }
     } catch (err) {
       setError('Failed to mint tokens');
@@ -47,18 +49,22 @@ function TokenManager() {
 
   const handleBurn = async () => {
     try {
-      const tokenAmount = parseInt(amount);
+      const tokenAmount = Math.round(parseFloat(amount) * TOKEN_UNIT);
       if (isNaN(tokenAmount) || tokenAmount <= 0) {
         setError('Please enter a valid amount');
         return;
       }
 
-      const result = await canister.burn_tokens(tokenAmount);
-      if (result.Ok) {
-        setSuccess(`Successfully burned ${result.Ok} tokens`);
+      const result = await canister.burn_tokens(BigInt(tokenAmount), []);
+      if ('Ok' in result) {
+        const { net_payout, total_penalty, total_fee } = result.Ok;
+        setSuccess(
+          `Received ${formatTokens(net_payout)} tokens ` +
+          `(penalty ${formatTokens(total_penalty)}, fee ${formatTokens(total_fee)})`
+        );
         fetchBalance();
       } else {
-        setError(result.Err);
+        setError(errorMessage(result.Err));
       }
     } catch (err) {
       setError('Failed to burn tokens');
@@ -68,11 +74,11 @@ function TokenManager() {
   const handleApplyRewards = async () => {
     try {
       const result = await canister.apply_rewards();
-      if (result.Ok) {
-        setSuccess(`Successfully applied ${result.Ok} rewards`);
+      if ('Ok' in result) {
+        setSuccess(`Successfully applied ${formatTokens(result.Ok)} tokens of rewards`);
         fetchBalance();
       } else {
-        setError(result.Err);
+        setError(errorMessage(result.Err));
       }
     } catch (err) {
       setError('Failed to apply rewards');
@@ -89,10 +95,12 @@ function TokenManager() {
       {balance && (
         <div className="balance-info">
           <h3>Your Balance</h3>
-          <p>Total Balance: {balance.total_balance}</p>
-          <p>Locked Balance: {balance.locked_balance}</p>
-          <p>Available Balance: {balance.available_balance}</p>
-          <p>Rewards Earned: {balance.rewards_earned}</p>
+          <p>Total Balance: {formatTokens(balance.total_balance)}</p>
+          <p>Locked Balance: {formatTokens(balance.locked_balance)}</p>
+          <p>Available Balance: {formatTokens(balance.available_balance)}</p>
+          <p>Rewards Earned: {formatTokens(balance.rewards_earned)}</p>
+          <p>Loyalty Bonus Earned: {formatTokens(balance.loyalty_bonus_earned)}</p>
+          <p>Loyalty Streak: {balance.loyalty_streak} terms</p>
         </div>
       )}
 
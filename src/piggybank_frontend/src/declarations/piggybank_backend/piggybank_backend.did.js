@@ -1,32 +1,539 @@
 export const idlFactory = ({ IDL }) => {
-  const LockPeriod = IDL.Variant({
-    'ThreeMonths': IDL.Null,
-    'SixMonths': IDL.Null,
-    'TwelveMonths': IDL.Null,
+  const ProductRef = IDL.Variant({
+    'Id' : IDL.Nat64,
+    'TwelveMonths' : IDL.Null,
+    'ThreeMonths' : IDL.Null,
+    'SixMonths' : IDL.Null,
   });
-
+  const MaturityInstruction = IDL.Variant({
+    'RenewPrincipal' : IDL.Null,
+    'PayOut' : IDL.Null,
+    'RenewAs' : ProductRef,
+    'Renew' : IDL.Null,
+  });
+  const MintResult = IDL.Record({
+    'fee' : IDL.Nat64,
+    'deposited' : IDL.Nat64,
+    'token_amount' : IDL.Nat64,
+    'deposit_id' : IDL.Nat64,
+  });
+  const Operation = IDL.Variant({
+    'Minting' : IDL.Null,
+    'Withdrawals' : IDL.Null,
+    'Rewards' : IDL.Null,
+  });
+  const Error = IDL.Variant({
+    'Paused' : IDL.Record({ 'operation' : Operation, 'reason' : IDL.Text }),
+    'Rejected' : IDL.Text,
+    'Unauthenticated' : IDL.Null,
+  });
+  const DepositTerms = IDL.Record({
+    'product_id' : IDL.Nat64,
+    'early_withdrawal_penalty_bps' : IDL.Nat32,
+    'duration_nanos' : IDL.Nat64,
+    'interest_rate_bps' : IDL.Nat32,
+  });
+  const WithdrawalStrategy = IDL.Variant({
+    'Fifo' : IDL.Null,
+    'Lifo' : IDL.Null,
+    'MaturedFirst' : IDL.Null,
+    'LeastPenalty' : IDL.Null,
+  });
+  const WithdrawalLeg = IDL.Record({
+    'fee' : IDL.Nat64,
+    'deposit_id' : IDL.Nat64,
+    'penalty' : IDL.Nat64,
+    'product_id' : IDL.Nat64,
+    'forfeited_interest' : IDL.Nat64,
+    'amount' : IDL.Nat64,
+  });
+  const WithdrawalQuote = IDL.Record({
+    'net_payout' : IDL.Nat64,
+    'legs' : IDL.Vec(WithdrawalLeg),
+    'total_fee' : IDL.Nat64,
+    'total_penalty' : IDL.Nat64,
+    'forfeited_interest' : IDL.Nat64,
+    'amount' : IDL.Nat64,
+  });
+  const DepositStatus = IDL.Variant({ 'Matured' : IDL.Null, 'Locked' : IDL.Null });
   const TokenDeposit = IDL.Record({
-    'amount': IDL.Nat64,
-    'lock_period': LockPeriod,
-    'deposit_time': IDL.Nat64,
-    'interest_rate': IDL.Float64,
-    'early_withdrawal_penalty': IDL.Float64,
+    'id' : IDL.Nat64,
+    'status' : DepositStatus,
+    'product_id' : IDL.Nat64,
+    'accrual_remainder' : IDL.Nat,
+    'lock_duration_nanos' : IDL.Nat64,
+    'deposit_time' : IDL.Nat64,
+    'early_withdrawal_penalty_bps' : IDL.Nat32,
+    'amount' : IDL.Nat64,
+    'maturity_instruction' : MaturityInstruction,
+    'partially_withdrawn' : IDL.Bool,
+    'last_accrual_time' : IDL.Nat64,
+    'reward_index' : IDL.Nat64,
+    'accrued_interest' : IDL.Nat64,
+    'interest_rate_bps' : IDL.Nat32,
   });
-
   const UserBalance = IDL.Record({
-    'total_balance': IDL.Nat64,
-    'locked_balance': IDL.Nat64,
-    'available_balance': IDL.Nat64,
-    'deposits': IDL.Vec(TokenDeposit),
-    'rewards_earned': IDL.Nat64,
+    'available_balance' : IDL.Nat64,
+    'rewards_earned' : IDL.Nat64,
+    'locked_balance' : IDL.Nat64,
+    'loyalty_streak' : IDL.Nat32,
+    'total_balance' : IDL.Nat64,
+    'deposits' : IDL.Vec(TokenDeposit),
+    'loyalty_bonus_earned' : IDL.Nat64,
+  });
+  const Product = IDL.Record({
+    'id' : IDL.Nat64,
+    'min_deposit' : IDL.Nat64,
+    'max_deposit' : IDL.Opt(IDL.Nat64),
+    'name' : IDL.Text,
+    'open' : IDL.Bool,
+    'early_withdrawal_penalty_bps' : IDL.Nat32,
+    'duration_nanos' : IDL.Nat64,
+    'interest_rate_bps' : IDL.Nat32,
+  });
+  const ProductArgs = IDL.Record({
+    'min_deposit' : IDL.Nat64,
+    'max_deposit' : IDL.Opt(IDL.Nat64),
+    'name' : IDL.Text,
+    'open' : IDL.Bool,
+    'early_withdrawal_penalty_bps' : IDL.Nat32,
+    'duration_nanos' : IDL.Nat64,
+    'interest_rate_bps' : IDL.Nat32,
+  });
+  const ProductRates = IDL.Record({
+    'product_id' : IDL.Nat64,
+    'early_withdrawal_penalty_bps' : IDL.Nat32,
+    'interest_rate_bps' : IDL.Nat32,
+  });
+  const RateSheet = IDL.Record({
+    'id' : IDL.Nat64,
+    'published_at' : IDL.Nat64,
+    'effective_from' : IDL.Nat64,
+    'rates' : IDL.Vec(ProductRates),
+  });
+  const Role = IDL.Variant({
+    'Operator' : IDL.Null,
+    'Auditor' : IDL.Null,
+    'Admin' : IDL.Null,
+    'Owner' : IDL.Null,
+  });
+  const FeeOperation = IDL.Variant({ 'Burn' : IDL.Null, 'Mint' : IDL.Null });
+  const AuditEvent = IDL.Variant({
+    'MaturitySweepRun' : IDL.Null,
+    'Paused' : IDL.Record({ 'operation' : Operation, 'reason' : IDL.Text }),
+    'RoleRevoked' : IDL.Record({ 'principal' : IDL.Principal, 'role' : Role }),
+    'Resumed' : IDL.Record({ 'operation' : Operation }),
+    'FeeRewardPoolShareSet' : IDL.Record({ 'share_bps' : IDL.Nat32 }),
+    'RoleGranted' : IDL.Record({ 'principal' : IDL.Principal, 'role' : Role }),
+    'ProductUpdated' : IDL.Record({ 'product_id' : IDL.Nat64 }),
+    'FeeScheduleSet' : IDL.Record({
+      'product_id' : IDL.Opt(IDL.Nat64),
+      'operation' : FeeOperation,
+    }),
+    'LoyaltyBonusRateSet' : IDL.Record({ 'bonus_rate_bps' : IDL.Nat32 }),
+    'ReferralSettingsSet' : IDL.Null,
+    'RewardPoolFunded' : IDL.Record({ 'amount' : IDL.Nat64 }),
+    'RateSheetPublished' : IDL.Record({ 'rate_sheet_id' : IDL.Nat64 }),
+    'PenaltyRewardPoolShareSet' : IDL.Record({ 'share_bps' : IDL.Nat32 }),
+    'ProductAdded' : IDL.Record({ 'product_id' : IDL.Nat64 }),
+  });
+  const AuditEntry = IDL.Record({
+    'id' : IDL.Nat64,
+    'time' : IDL.Nat64,
+    'event' : AuditEvent,
+    'caller' : IDL.Principal,
+  });
+  const RewardPool = IDL.Record({
+    'balance' : IDL.Nat64,
+    'referral_bonuses' : IDL.Nat64,
+    'fees' : IDL.Nat64,
+    'funded' : IDL.Nat64,
+    'loyalty_bonuses' : IDL.Nat64,
+    'shortfall' : IDL.Nat64,
+    'interest_paid' : IDL.Nat64,
+    'forfeited_interest' : IDL.Nat64,
+    'penalties' : IDL.Nat64,
+  });
+  const RewardPoolStatus = IDL.Record({
+    'pool' : RewardPool,
+    'uncovered' : IDL.Nat64,
+    'projected_liabilities' : IDL.Nat64,
+  });
+  const FeeSchedule = IDL.Record({
+    'rate_bps' : IDL.Nat32,
+    'min_fee' : IDL.Nat64,
+    'max_fee' : IDL.Opt(IDL.Nat64),
+  });
+  const FeeScheduleEntry = IDL.Record({
+    'product_id' : IDL.Opt(IDL.Nat64),
+    'operation' : FeeOperation,
+    'schedule' : FeeSchedule,
+  });
+  const FeeSettings = IDL.Record({
+    'schedules' : IDL.Vec(FeeScheduleEntry),
+    'reward_pool_share_bps' : IDL.Nat32,
+  });
+  const FeeTreasury = IDL.Record({
+    'balance' : IDL.Nat64,
+    'to_reward_pool' : IDL.Nat64,
+    'burn_fees' : IDL.Nat64,
+    'mint_fees' : IDL.Nat64,
+  });
+  const PenaltyAccount = IDL.Record({
+    'total' : IDL.Nat64,
+    'balance' : IDL.Nat64,
+    'to_reward_pool' : IDL.Nat64,
+    'by_product' : IDL.Vec(IDL.Tuple(IDL.Nat64, IDL.Nat64)),
+  });
+  const PenaltyAccountStatus = IDL.Record({
+    'reward_pool_share_bps' : IDL.Nat32,
+    'account' : PenaltyAccount,
+  });
+  const LoyaltySettings = IDL.Record({ 'bonus_rate_bps' : IDL.Nat32 });
+  const User = IDL.Record({
+    'id' : IDL.Principal,
+    'updated_at' : IDL.Nat64,
+    'username' : IDL.Text,
+    'created_at' : IDL.Nat64,
+    'email' : IDL.Text,
+  });
+  const CreateGoalArgs = IDL.Record({
+    'name' : IDL.Text,
+    'deadline' : IDL.Opt(IDL.Nat64),
+    'target_amount' : IDL.Nat64,
+    'category' : IDL.Text,
+    'product' : IDL.Opt(ProductRef),
+  });
+  const GoalStatus = IDL.Variant({ 'Active' : IDL.Null, 'Completed' : IDL.Null });
+  const SavingsGoal = IDL.Record({
+    'id' : IDL.Nat64,
+    'status' : GoalStatus,
+    'name' : IDL.Text,
+    'deadline' : IDL.Opt(IDL.Nat64),
+    'created_at' : IDL.Nat64,
+    'user_id' : IDL.Principal,
+    'current_amount' : IDL.Nat64,
+    'target_amount' : IDL.Nat64,
+    'category' : IDL.Text,
+    'product' : ProductRef,
+    'deposit_ids' : IDL.Vec(IDL.Nat64),
+  });
+  const DepositArgs = IDL.Record({ 'goal_id' : IDL.Nat64, 'amount' : IDL.Nat64 });
+  const TransactionType = IDL.Variant({
+    'Fee' : IDL.Null,
+    'Reward' : IDL.Null,
+    'Withdrawal' : IDL.Null,
+    'Contribution' : IDL.Null,
+    'Penalty' : IDL.Null,
+  });
+  const Transaction = IDL.Record({
+    'id' : IDL.Nat64,
+    'transaction_type' : TransactionType,
+    'goal_id' : IDL.Opt(IDL.Nat64),
+    'note' : IDL.Opt(IDL.Text),
+    'user_id' : IDL.Principal,
+    'timestamp' : IDL.Nat64,
+    'manual' : IDL.Bool,
+    'amount' : IDL.Nat64,
+  });
+  const TransactionSummary = IDL.Record({
+    'contributions' : IDL.Nat64,
+    'fees' : IDL.Nat64,
+    'withdrawals' : IDL.Nat64,
+    'rewards' : IDL.Nat64,
+    'penalties' : IDL.Nat64,
+  });
+  const NotificationType = IDL.Variant({
+    'DepositMatured' : IDL.Null,
+    'PenaltyApplied' : IDL.Null,
+    'DeadlineApproaching' : IDL.Null,
+    'GoalCompleted' : IDL.Null,
+    'RewardsCredited' : IDL.Null,
+    'MilestoneReached' : IDL.Null,
+  });
+  const Notification = IDL.Record({
+    'id' : IDL.Nat64,
+    'goal_id' : IDL.Opt(IDL.Nat64),
+    'read' : IDL.Bool,
+    'created_at' : IDL.Nat64,
+    'user_id' : IDL.Principal,
+    'notification_type' : NotificationType,
+    'message' : IDL.Text,
+  });
+  const ReferralStatus = IDL.Variant({
+    'Unqualified' : IDL.Null,
+    'Rewarded' : IDL.Record({ 'referrer_bonus' : IDL.Nat64, 'referee_bonus' : IDL.Nat64 }),
+    'Unpaid' : IDL.Null,
+    'Pending' : IDL.Null,
+  });
+  const Referral = IDL.Record({
+    'status' : ReferralStatus,
+    'referrer' : IDL.Principal,
+    'redeemed_at' : IDL.Nat64,
+    'referee' : IDL.Principal,
+  });
+  const ReferralEarnings = IDL.Record({
+    'code' : IDL.Opt(IDL.Text),
+    'referred_by' : IDL.Opt(IDL.Principal),
+    'referrals' : IDL.Vec(Referral),
+    'earned_as_referrer' : IDL.Nat64,
+    'earned_as_referee' : IDL.Nat64,
+  });
+  const ReferralSettings = IDL.Record({
+    'min_deposit' : IDL.Nat64,
+    'min_lock_nanos' : IDL.Nat64,
+    'referrer_bonus' : IDL.Nat64,
+    'referee_bonus' : IDL.Nat64,
+  });
+  const Pause = IDL.Record({
+    'paused_at' : IDL.Nat64,
+    'paused_by' : IDL.Principal,
+    'reason' : IDL.Text,
+  });
+  const PauseState = IDL.Record({
+    'minting' : IDL.Opt(Pause),
+    'withdrawals' : IDL.Opt(Pause),
+    'rewards' : IDL.Opt(Pause),
   });
-
   return IDL.Service({
-    'mint_tokens': IDL.Func([IDL.Nat64, LockPeriod], [IDL.Variant({ 'Ok': IDL.Nat64, 'Err': IDL.Text })], []),
-    'burn_tokens': IDL.Func([IDL.Nat64], [IDL.Variant({ 'Ok': IDL.Nat64, 'Err': IDL.Text })], []),
-    'get_balance': IDL.Func([], [IDL.Variant({ 'Ok': UserBalance, 'Err': IDL.Text })], ['query']),
-    'apply_rewards': IDL.Func([], [IDL.Variant({ 'Ok': IDL.Nat64, 'Err': IDL.Text })], []),
+    'add_product' : IDL.Func(
+        [ProductArgs],
+        [IDL.Variant({ 'Ok' : IDL.Nat64, 'Err' : Error })],
+        [],
+      ),
+    'add_transaction' : IDL.Func(
+        [IDL.Nat64, IDL.Nat64, TransactionType, IDL.Opt(IDL.Text)],
+        [IDL.Variant({ 'Ok' : Transaction, 'Err' : Error })],
+        [],
+      ),
+    'apply_rewards' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : IDL.Nat64, 'Err' : Error })],
+        [],
+      ),
+    'burn_tokens' : IDL.Func(
+        [IDL.Nat64, IDL.Opt(WithdrawalStrategy)],
+        [IDL.Variant({ 'Ok' : WithdrawalQuote, 'Err' : Error })],
+        [],
+      ),
+    'create_referral_code' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : IDL.Text, 'Err' : Error })],
+        [],
+      ),
+    'create_savings_goal' : IDL.Func(
+        [CreateGoalArgs],
+        [IDL.Variant({ 'Ok' : SavingsGoal, 'Err' : Error })],
+        [],
+      ),
+    'deposit' : IDL.Func(
+        [DepositArgs],
+        [IDL.Variant({ 'Ok' : SavingsGoal, 'Err' : Error })],
+        [],
+      ),
+    'find_user' : IDL.Func([IDL.Text], [IDL.Opt(IDL.Principal)], ['query']),
+    'fund_reward_pool' : IDL.Func(
+        [IDL.Nat64],
+        [IDL.Variant({ 'Ok' : IDL.Nat64, 'Err' : Error })],
+        [],
+      ),
+    'get_audit_log' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : IDL.Vec(AuditEntry), 'Err' : Error })],
+        ['query'],
+      ),
+    'get_balance' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : UserBalance, 'Err' : Error })],
+        ['query'],
+      ),
+    'get_custom_lock_terms' : IDL.Func(
+        [IDL.Nat64],
+        [IDL.Variant({ 'Ok' : DepositTerms, 'Err' : Error })],
+        ['query'],
+      ),
+    'get_fee_settings' : IDL.Func([], [FeeSettings], ['query']),
+    'get_fee_treasury' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : FeeTreasury, 'Err' : Error })],
+        ['query'],
+      ),
+    'get_loyalty_settings' : IDL.Func([], [LoyaltySettings], ['query']),
+    'get_my_roles' : IDL.Func([], [IDL.Vec(Role)], ['query']),
+    'get_notifications' : IDL.Func(
+        [IDL.Bool],
+        [IDL.Vec(Notification)],
+        ['query'],
+      ),
+    'get_pause_state' : IDL.Func([], [PauseState], ['query']),
+    'get_penalty_account' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : PenaltyAccountStatus, 'Err' : Error })],
+        ['query'],
+      ),
+    'get_products' : IDL.Func([], [IDL.Vec(Product)], ['query']),
+    'get_rate_sheets' : IDL.Func([], [IDL.Vec(RateSheet)], ['query']),
+    'get_rates_at' : IDL.Func(
+        [IDL.Opt(IDL.Nat64)],
+        [IDL.Vec(ProductRates)],
+        ['query'],
+      ),
+    'get_referral_earnings' : IDL.Func([], [ReferralEarnings], ['query']),
+    'get_referral_settings' : IDL.Func([], [ReferralSettings], ['query']),
+    'get_reward_indices' : IDL.Func(
+        [],
+        [IDL.Vec(IDL.Tuple(IDL.Nat64, IDL.Nat64))],
+        ['query'],
+      ),
+    'get_reward_pool' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : RewardPoolStatus, 'Err' : Error })],
+        ['query'],
+      ),
+    'get_roles' : IDL.Func(
+        [],
+        [IDL.Variant({
+          'Ok' : IDL.Vec(IDL.Tuple(IDL.Principal, IDL.Vec(Role))),
+          'Err' : Error,
+        })],
+        ['query'],
+      ),
+    'get_savings_goal' : IDL.Func(
+        [IDL.Nat64],
+        [IDL.Opt(SavingsGoal)],
+        ['query'],
+      ),
+    'get_schema_version' : IDL.Func([], [IDL.Nat32], ['query']),
+    'get_transaction' : IDL.Func(
+        [IDL.Nat64],
+        [IDL.Opt(Transaction)],
+        ['query'],
+      ),
+    'get_transaction_summary' : IDL.Func(
+        [IDL.Nat64],
+        [IDL.Variant({ 'Ok' : TransactionSummary, 'Err' : Error })],
+        ['query'],
+      ),
+    'get_user' : IDL.Func([IDL.Principal], [IDL.Opt(User)], ['query']),
+    'grant_role' : IDL.Func(
+        [IDL.Principal, Role],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'list_savings_goals' : IDL.Func([], [IDL.Vec(SavingsGoal)], ['query']),
+    'list_transactions' : IDL.Func(
+        [IDL.Opt(IDL.Nat64)],
+        [IDL.Vec(Transaction)],
+        ['query'],
+      ),
+    'mark_notification_as_read' : IDL.Func(
+        [IDL.Nat64],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'mint_tokens' : IDL.Func(
+        [IDL.Nat64, ProductRef, IDL.Opt(MaturityInstruction)],
+        [IDL.Variant({ 'Ok' : MintResult, 'Err' : Error })],
+        [],
+      ),
+    'mint_tokens_until' : IDL.Func(
+        [IDL.Nat64, IDL.Nat64, IDL.Opt(MaturityInstruction)],
+        [IDL.Variant({ 'Ok' : MintResult, 'Err' : Error })],
+        [],
+      ),
+    'pause_operation' : IDL.Func(
+        [Operation, IDL.Text],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'publish_rate_sheet' : IDL.Func(
+        [IDL.Nat64, IDL.Vec(ProductRates)],
+        [IDL.Variant({ 'Ok' : IDL.Nat64, 'Err' : Error })],
+        [],
+      ),
+    'quote_withdrawal' : IDL.Func(
+        [IDL.Nat64, IDL.Opt(WithdrawalStrategy)],
+        [IDL.Variant({ 'Ok' : WithdrawalQuote, 'Err' : Error })],
+        ['query'],
+      ),
+    'redeem_referral_code' : IDL.Func(
+        [IDL.Text],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'register_user' : IDL.Func(
+        [IDL.Text, IDL.Text],
+        [IDL.Variant({ 'Ok' : User, 'Err' : Error })],
+        [],
+      ),
+    'resume_operation' : IDL.Func(
+        [Operation],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'revoke_role' : IDL.Func(
+        [IDL.Principal, Role],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'run_maturity_sweep' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'set_fee_reward_pool_share' : IDL.Func(
+        [IDL.Nat32],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'set_fee_schedule' : IDL.Func(
+        [FeeOperation, IDL.Opt(IDL.Nat64), IDL.Opt(FeeSchedule)],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'set_loyalty_bonus_rate' : IDL.Func(
+        [IDL.Nat32],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'set_maturity_instruction' : IDL.Func(
+        [IDL.Nat64, MaturityInstruction],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'set_penalty_reward_pool_share' : IDL.Func(
+        [IDL.Nat32],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'set_referral_settings' : IDL.Func(
+        [ReferralSettings],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'update_product' : IDL.Func(
+        [IDL.Nat64, ProductArgs],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : Error })],
+        [],
+      ),
+    'update_user' : IDL.Func(
+        [IDL.Text],
+        [IDL.Variant({ 'Ok' : User, 'Err' : Error })],
+        [],
+      ),
+    'withdraw_deposit' : IDL.Func(
+        [IDL.Nat64, IDL.Nat64],
+        [IDL.Variant({ 'Ok' : WithdrawalQuote, 'Err' : Error })],
+        [],
+      ),
+  });
+};
+export const init = ({ IDL }) => {
+  const InitArgs = IDL.Record({
+    'owner' : IDL.Principal,
+    'auditors' : IDL.Vec(IDL.Principal),
+    'operators' : IDL.Vec(IDL.Principal),
+    'admins' : IDL.Vec(IDL.Principal),
   });
+  return [IDL.Opt(InitArgs)];
 };
-
-export const init = ({ IDL }) => { return []; };
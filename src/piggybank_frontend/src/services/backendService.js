@@ -5,6 +5,22 @@ import { Principal } from "@dfinity/principal";
 const CANISTER_ID = import.meta.env.VITE_CANISTER_ID_PIGGYBANK_BACKEND;
 const HOST = import.meta.env.VITE_DFX_NETWORK === "local" ? "http://127.0.0.1:4943" : "https://ic0.app";
 
+// Token amounts are in minor units, with 6 decimals.
+export const TOKEN_UNIT = 1_000_000;
+
+export const formatTokens = (amount) => (Number(amount) / TOKEN_UNIT).toFixed(2);
+
+// Turns the canister's `Error` variant into a message for the user.
+export const errorMessage = (error) => {
+    if ("Unauthenticated" in error) {
+        return "Please log in first";
+    }
+    if ("Paused" in error) {
+        return `Temporarily unavailable: ${error.Paused.reason}`;
+    }
+    return error.Rejected;
+};
+
 class BackendService {
     constructor() {
         this.actor = null;
@@ -67,12 +83,19 @@ class BackendService {
             console.log("Balance result:", result);
             if ("Ok" in result) {
                 return result.Ok;
-            } else if (result.Err.includes("User balance not found")) {
-                // Initialize user balance if not found
-                await this.mintTokens(BigInt(0), { ThreeMonths: null });
-                return await this.getBalance(); // Retry getting balance
+            } else if (result.Err.Rejected === "User balance not found") {
+                // No deposits yet
+                return {
+                    total_balance: BigInt(0),
+                    locked_balance: BigInt(0),
+                    available_balance: BigInt(0),
+                    deposits: [],
+                    rewards_earned: BigInt(0),
+                    loyalty_bonus_earned: BigInt(0),
+                    loyalty_streak: 0
+                };
             } else {
-                throw new Error(result.Err);
+                throw new Error(errorMessage(result.Err));
             }
         } catch (error) {
             console.error("Error getting balance:", error);
@@ -80,7 +103,12 @@ class BackendService {
         }
     }
 
-    async mintTokens(amount, lockPeriod) {
+    // `amount` is in whole rupees; `product` is a `ProductRef`, such as
+    // `{ ThreeMonths: null }` or `{ Id: 3n }`; `maturityInstruction` is an
+    // optional `MaturityInstruction`, such as `{ Renew: null }`. Returns a
+    // `MintResult` with the tokens minted, the fee and the tokens locked, in
+    // minor units.
+    async mintTokens(amount, product, maturityInstruction) {
         try {
             await this.ensureInitialized();
             // Ensure amount is BigInt
@@ -89,14 +117,18 @@ class BackendService {
                 amount: mintAmount.toString(),
                 amountType: typeof mintAmount,
                 isBigInt: mintAmount instanceof BigInt,
-                lockPeriod 
+                product
             });
-            const result = await this.actor.mint_tokens(mintAmount, lockPeriod);
+            const result = await this.actor.mint_tokens(
+                mintAmount,
+                product,
+                maturityInstruction ? [maturityInstruction] : []
+            );
             console.log("Mint result:", result);
             if ("Ok" in result) {
                 return result.Ok;
             } else {
-                throw new Error(result.Err);
+                throw new Error(errorMessage(result.Err));
             }
         } catch (error) {
             console.error("Error minting tokens:", error);
@@ -104,7 +136,10 @@ class BackendService {
         }
     }
 
-    async burnTokens(amount) {
+    // `amount` is in token minor units; `strategy` is an optional
+    // `WithdrawalStrategy`, such as `{ Fifo: null }`. Returns the executed
+    // `WithdrawalQuote`, with the penalty, fee and net payout.
+    async burnTokens(amount, strategy) {
         if (!this.initialized) {
             throw new Error("Service not initialized");
         }
@@ -131,12 +166,12 @@ class BackendService {
                 throw new Error("Insufficient balance");
             }
 
-            const result = await this.actor.burn_tokens(burnAmount);
+            const result = await this.actor.burn_tokens(burnAmount, strategy ? [strategy] : []);
             console.log("Burn tokens result:", result);
 
             if (result.Err) {
                 console.error("Error burning tokens:", result.Err);
-                throw new Error(result.Err);
+                throw new Error(errorMessage(result.Err));
             }
 
             // Verify the balance after burn
@@ -146,7 +181,7 @@ class BackendService {
                 available: newBalance?.available_balance?.toString()
             });
 
-            return true;
+            return result.Ok;
         } catch (error) {
             console.error("Error in burnTokens:", error);
             throw error;
@@ -162,7 +197,7 @@ class BackendService {
             if ("Ok" in result) {
                 return result.Ok;
             } else {
-                throw new Error(result.Err);
+                throw new Error(errorMessage(result.Err));
             }
         } catch (error) {
             console.error("Error applying rewards:", error);